@@ -1,7 +1,7 @@
 // use crate::db::Database;
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use eframe::{App, CreationContext};
 use egui::Context;
@@ -9,12 +9,23 @@ use egui::ViewportCommand;
 use egui_file_dialog as efd;
 use egui_tiles as et;
 use shah::error::SystemError;
+use shah::models::Gene;
 
-use crate::db::DbTile;
+use crate::db::nav::{self, Link};
+use crate::db::worker::Worker;
+use crate::db::{custom, DbTile};
 use crate::fonts;
 use crate::shortcuts as sc;
 use crate::tiles;
 use crate::utils::db_name;
+use crate::workspace::{self, Workspace};
+
+/// a clicked gene and the databases it may point to
+struct GenePick {
+    gene: Gene,
+    /// (title, path, link) of every entity database with a gene field
+    targets: Vec<(String, PathBuf, Link)>,
+}
 
 // #[derive(Default)]
 pub struct ShahApp {
@@ -27,10 +38,27 @@ pub struct ShahApp {
     cpu_usage: f32,
     db_paths: HashMap<String, HashMap<String, Vec<(String, PathBuf)>>>,
     file_dialog: egui_file_dialog::FileDialog,
+    /// the paths picked for the side panel
+    roots: Vec<PathBuf>,
+    /// the workspace file that was last opened or saved
+    workspace: Option<PathBuf>,
+    workspace_dialog: egui_file_dialog::FileDialog,
+    /// the last open or save that failed
+    open_error: Option<String>,
+    gene_pick: Option<GenePick>,
+    /// resolves a clicked gene against (gene, paths) off the ui thread
+    resolver: Worker<(Gene, Vec<PathBuf>), GenePick>,
+    /// the gene being resolved
+    picking: Option<Gene>,
+    /// the side panel changed since the gene links were told about it
+    known_stale: bool,
 }
 
 impl ShahApp {
-    pub fn new(cc: &CreationContext<'_>) -> Result<Self, SystemError> {
+    pub fn new(
+        cc: &CreationContext<'_>, workspace: Option<PathBuf>,
+        paths: Vec<PathBuf>,
+    ) -> Result<Self, SystemError> {
         cc.egui_ctx.style_mut(|style| {
             let w = 8.0;
             style.spacing.scroll.bar_width = w;
@@ -54,9 +82,21 @@ impl ShahApp {
 
         let config = crate::config::config();
 
+        for ((prefix, db), name) in config.decoders.iter() {
+            let Some(decoder) = custom::builtin(name) else {
+                log::error!("unknown decoder: {name} for {prefix}-{db}");
+                continue;
+            };
+            custom::register(*prefix, *db, decoder);
+        }
+
         let mut file_dialog = efd::FileDialog::new()
             .anchor(egui::Align2::CENTER_CENTER, (0.0, 0.0))
             .title("Select Databases");
+        let mut workspace_dialog = efd::FileDialog::new()
+            .anchor(egui::Align2::CENTER_CENTER, (0.0, 0.0))
+            .title("Workspace")
+            .default_file_name("workspace.toml");
 
         if !config.quick_access.is_empty() {
             file_dialog = file_dialog.add_quick_access("Quick Access", |qa| {
@@ -66,10 +106,11 @@ impl ShahApp {
             });
 
             let init = config.quick_access[0].1.clone();
-            file_dialog = file_dialog.initial_directory(init);
+            file_dialog = file_dialog.initial_directory(init.clone());
+            workspace_dialog = workspace_dialog.initial_directory(init);
         }
 
-        let app = Self {
+        let mut app = Self {
             settings: false,
             fullscreen: false,
             side_panel: true,
@@ -79,35 +120,117 @@ impl ShahApp {
             cpu_usage: 0.0,
             db_paths: HashMap::new(),
             file_dialog,
+            roots: Vec::new(),
+            workspace: None,
+            workspace_dialog,
+            open_error: None,
+            gene_pick: None,
+            resolver: Worker::start(|(gene, paths)| resolve(gene, paths)),
+            picking: None,
+            known_stale: true,
         };
 
         // app.add_db_path("/home/i007c/projects/00-team/shah/data/".into());
+        if let Some(path) = workspace {
+            app.open_workspace(path, &cc.egui_ctx);
+        }
+        app.open_paths(paths, &cc.egui_ctx);
 
         Ok(app)
     }
 
-    fn add_database(&mut self, path: PathBuf) {
-        let old = self.tree.tiles.iter().find_map(|(tid, t)| {
-            if let et::Tile::Pane(p) = t {
-                if p.path == path {
-                    return Some(tid);
-                }
-            }
-            None
-        });
-
-        if let Some(id) = old {
-            self.tree.tiles.remove(*id);
+    fn add_database(&mut self, path: PathBuf, ctx: &Context) {
+        if let Some(id) = self.find_pane(&path) {
+            self.tree.tiles.remove(id);
         }
 
-        let db = match DbTile::new(path) {
+        let db = match DbTile::new(path.clone(), ctx) {
             Ok(v) => v,
             Err(e) => {
                 log::error!("error init new database: {e:#?}");
+                self.push_error(format!("could not open {path:?}: {e:?}"));
                 return;
             }
         };
+        self.insert_pane(db);
+    }
+
+    /// lists directories in the side panel and opens files as tiles
+    fn open_paths(&mut self, paths: Vec<PathBuf>, ctx: &Context) {
+        let mut missing = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                self.add_db_path(path);
+            } else if path.is_file() {
+                self.add_database(path, ctx);
+            } else {
+                missing.push(format!("{path:?}"));
+            }
+        }
+        if !missing.is_empty() {
+            self.push_error(format!("not found: {}", missing.join(", ")));
+        }
+    }
+
+    /// shows `e` after the errors that are already in the header
+    fn push_error(&mut self, e: String) {
+        self.open_error = Some(match self.open_error.take() {
+            Some(old) => format!("{old} | {e}"),
+            None => e,
+        });
+    }
 
+    /// opens the files dropped onto the window, hints at the drop while
+    /// they are dragged over it
+    fn dropped_files(&mut self, ctx: &Context) {
+        let (hovered, dropped) = ctx.input(|i| {
+            let paths = |files: &[egui::DroppedFile]| -> Vec<PathBuf> {
+                files.iter().filter_map(|f| f.path.clone()).collect()
+            };
+            (!i.raw.hovered_files.is_empty(), paths(&i.raw.dropped_files))
+        });
+        if hovered {
+            let layer = egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("drop-files"),
+            );
+            let painter = ctx.layer_painter(layer);
+            let rect = ctx.screen_rect();
+            painter.rect_filled(
+                rect,
+                0.0,
+                egui::Color32::from_black_alpha(160),
+            );
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "drop to open",
+                egui::TextStyle::Heading.resolve(&ctx.style()),
+                egui::Color32::WHITE,
+            );
+        }
+        if !dropped.is_empty() {
+            self.open_paths(dropped, ctx);
+        }
+    }
+
+    /// shows the integrity report of `path`, running it again if it is open
+    fn open_report(&mut self, path: PathBuf) {
+        let old = self.tree.tiles.iter().find_map(|(tid, t)| match t {
+            et::Tile::Pane(p) if p.path == path && p.is_report() => Some(*tid),
+            _ => None,
+        });
+        let Some(tid) = old else {
+            self.insert_pane(DbTile::report(path));
+            return;
+        };
+        self.tree.make_active(|t, _| t == tid);
+        if let Some(et::Tile::Pane(p)) = self.tree.tiles.get_mut(tid) {
+            p.kind.reload();
+        }
+    }
+
+    fn insert_pane(&mut self, db: DbTile) {
         let old_root = self.tree.root;
 
         let tab = vec![self.tree.tiles.insert_pane(db)];
@@ -119,6 +242,156 @@ impl ShahApp {
         }
     }
 
+    fn find_pane(&self, path: &Path) -> Option<et::TileId> {
+        self.tree.tiles.iter().find_map(|(tid, t)| match t {
+            et::Tile::Pane(p) if p.path == path && !p.is_report() => Some(*tid),
+            _ => None,
+        })
+    }
+
+    /// replaces the tiles and the side panel with the workspace at `path`
+    fn open_workspace(&mut self, path: PathBuf, ctx: &Context) {
+        let ws = match Workspace::load(&path) {
+            Ok(ws) => ws,
+            Err(e) => {
+                self.open_error = Some(e);
+                return;
+            }
+        };
+        self.db_paths.clear();
+        self.roots.clear();
+        self.add_db_paths(ws.roots.clone());
+
+        let (tree, errors) = ws.restore(ctx);
+        self.tree = tree;
+        self.open_error = (!errors.is_empty()).then(|| errors.join(" | "));
+        workspace::remember(ctx, &path);
+        self.workspace = Some(path);
+    }
+
+    fn save_workspace(&mut self, path: PathBuf, ctx: &Context) {
+        let ws = Workspace::capture(&self.tree, &self.roots);
+        match ws.save(&path) {
+            Ok(()) => {
+                workspace::remember(ctx, &path);
+                self.workspace = Some(path);
+            }
+            Err(e) => self.open_error = Some(e),
+        }
+    }
+
+    /// saves to the current workspace file, asks for one if there is none
+    fn save_workspace_here(&mut self, ctx: &Context) {
+        match self.workspace.clone() {
+            Some(path) => self.save_workspace(path, ctx),
+            None => self.workspace_dialog.save_file(),
+        }
+    }
+
+    /// resolves a clicked gene against the open and the known databases
+    fn pick_gene(&mut self, gene: Gene, ctx: &Context) {
+        let mut paths: Vec<PathBuf> = self
+            .tree
+            .tiles
+            .iter()
+            .filter_map(|(_, t)| match t {
+                et::Tile::Pane(p) => Some(p.path.clone()),
+                _ => None,
+            })
+            .collect();
+        for dbs in self.db_paths.values() {
+            for (_, p) in dbs.values().flatten() {
+                if !paths.contains(p) {
+                    paths.push(p.clone());
+                }
+            }
+        }
+        self.picking = Some(gene);
+        self.gene_pick = None;
+        self.resolver.request((gene, paths), ctx);
+    }
+
+    /// shows the item `id` of the database at `path`, opening it if needed
+    fn open_gene(&mut self, path: PathBuf, id: u64, ctx: &Context) {
+        if self.find_pane(&path).is_none() {
+            self.add_database(path.clone(), ctx);
+        }
+        let Some(tid) = self.find_pane(&path) else { return };
+        self.tree.make_active(|t, _| t == tid);
+        if let Some(et::Tile::Pane(p)) = self.tree.tiles.get_mut(tid) {
+            if !p.goto(id) {
+                self.open_error =
+                    Some(format!("{} has no item rows to jump to", p.title()));
+            }
+        }
+    }
+
+    fn show_gene_pick(&mut self, ctx: &Context) {
+        if let Some(pick) = self.resolver.poll() {
+            if self.picking.take().is_some() {
+                self.gene_pick = Some(pick);
+            }
+        }
+        if let Some(g) = self.picking {
+            let mut open = true;
+            egui::Window::new(format!(
+                "Gene({}, {}, {:?}, {})",
+                g.id, g.iter, g.pepper, g.server
+            ))
+            .id(egui::Id::new("gene-pick"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("looking through the known databases");
+                });
+            });
+            if !open {
+                self.picking = None;
+            }
+            return;
+        }
+        let Some(pick) = &self.gene_pick else { return };
+        let g = pick.gene;
+        let mut open = true;
+        let mut target = None;
+        egui::Window::new(format!(
+            "Gene({}, {}, {:?}, {})",
+            g.id, g.iter, g.pepper, g.server
+        ))
+        .id(egui::Id::new("gene-pick"))
+        .open(&mut open)
+        .scroll([false, true])
+        .show(ctx, |ui| {
+            if pick.targets.is_empty() {
+                ui.label("no known entity database has a gene field");
+            }
+            for (title, path, link) in pick.targets.iter() {
+                ui.horizontal(|ui| {
+                    let res = ui.add_enabled(
+                        link.followable(),
+                        egui::Button::new(title),
+                    );
+                    if res.on_hover_text(path.display().to_string()).clicked() {
+                        target = Some(path.clone());
+                    }
+                    let text = egui::RichText::new(link.describe());
+                    match link.color(ui.visuals()) {
+                        Some(c) => ui.label(text.color(c)),
+                        None => ui.label(text),
+                    };
+                });
+            }
+        });
+
+        if let Some(path) = target {
+            self.gene_pick = None;
+            self.open_gene(path, g.id.0, ctx);
+        } else if !open {
+            self.gene_pick = None;
+        }
+    }
+
     fn _add_db_path(
         &mut self, path: PathBuf, depth: usize, total: usize,
     ) -> usize {
@@ -130,7 +403,14 @@ impl ShahApp {
         }
 
         if path.is_dir() {
-            let mut it = path.read_dir().unwrap();
+            let mut it = match path.read_dir() {
+                Ok(it) => it,
+                Err(e) => {
+                    log::error!("could not list {path:?}: {e}");
+                    self.push_error(format!("could not list {path:?}: {e}"));
+                    return 0;
+                }
+            };
             let max = 1000 - total;
             let mut n = 0usize;
             while let Some(Ok(p)) = it.next() {
@@ -168,7 +448,25 @@ impl ShahApp {
     }
 
     pub fn add_db_path(&mut self, path: PathBuf) {
+        if !self.roots.contains(&path) {
+            self.roots.push(path.clone());
+        }
         self._add_db_path(path, 0, 0);
+        self.known_stale = true;
+    }
+
+    /// tells the gene links about every database in the side panel
+    fn set_known(&mut self, ctx: &Context) {
+        if !std::mem::take(&mut self.known_stale) {
+            return;
+        }
+        let mut known = Vec::new();
+        for (scope, dbs) in self.db_paths.iter() {
+            for (_, p) in dbs.values().flatten() {
+                known.push((scope.clone(), p.clone()));
+            }
+        }
+        nav::set_known(ctx, known);
     }
 
     pub fn add_db_paths(&mut self, paths: Vec<PathBuf>) {
@@ -178,6 +476,24 @@ impl ShahApp {
     }
 }
 
+/// the link of `gene` in every entity database at `paths`, best first
+fn resolve(gene: Gene, paths: Vec<PathBuf>) -> GenePick {
+    let rank = |l: &Link| match l {
+        Link::Valid => 0,
+        Link::Stale => 1,
+        Link::Broken(_) => 2,
+        Link::Dangling | Link::Empty => 3,
+    };
+    let mut targets = Vec::new();
+    for path in paths {
+        let Some(link) = nav::resolve(&path, &gene) else { continue };
+        let (scope, db, kind) = db_name(&path);
+        targets.push((format!("{scope}/{db}/{kind}"), path, link));
+    }
+    targets.sort_by_key(|(title, _, l)| (rank(l), title.clone()));
+    GenePick { gene, targets }
+}
+
 impl App for ShahApp {
     fn persist_egui_memory(&self) -> bool {
         true
@@ -192,6 +508,9 @@ impl App for ShahApp {
         if ctx.input_mut(|i| i.consume_shortcut(&sc::OPEN_FILE)) {
             self.file_dialog.pick_multiple();
         }
+        if ctx.input_mut(|i| i.consume_shortcut(&sc::SAVE_WORKSPACE)) {
+            self.save_workspace_here(ctx);
+        }
 
         ctx.send_viewport_cmd(ViewportCommand::Fullscreen(self.fullscreen));
         egui::Window::new("Settings")
@@ -211,6 +530,31 @@ impl App for ShahApp {
                         self.file_dialog.pick_multiple();
                     }
                     ui.menu_button("File", |ui| {
+                        if ui.button("open workspace").clicked() {
+                            self.workspace_dialog.pick_file();
+                            ui.close_menu();
+                        }
+                        let recent = workspace::recent(ctx);
+                        ui.add_enabled_ui(!recent.is_empty(), |ui| {
+                            ui.menu_button("recent workspaces", |ui| {
+                                for path in recent {
+                                    let text = path.display().to_string();
+                                    if ui.button(text).clicked() {
+                                        self.open_workspace(path, ctx);
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                        });
+                        if ui.button("save workspace").clicked() {
+                            self.save_workspace_here(ctx);
+                            ui.close_menu();
+                        }
+                        if ui.button("save workspace as").clicked() {
+                            self.workspace_dialog.save_file();
+                            ui.close_menu();
+                        }
+                        ui.separator();
                         if ui.button("settings").clicked() {
                             self.settings = !self.settings;
                         }
@@ -225,6 +569,18 @@ impl App for ShahApp {
                         self.cpu_usage = cpu * 1e3;
                     }
                     ui.label(format!("cpu usage: {}ms", self.cpu_usage));
+                    if let Some(path) = &self.workspace {
+                        let name = path.file_stem().unwrap_or_default();
+                        ui.label(format!("workspace: {}", name.display()))
+                            .on_hover_text(path.display().to_string());
+                    }
+                    if let Some(e) = &self.open_error {
+                        ui.separator();
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                        if ui.small_button("x").clicked() {
+                            self.open_error = None;
+                        }
+                    }
                 })
             });
 
@@ -249,7 +605,7 @@ impl App for ShahApp {
                                     let (tit, p) = &pfs[0];
                                     let n = format!("{db}/{tit}");
                                     if ui.button(n).clicked() {
-                                        self.add_database(p.clone());
+                                        self.add_database(p.clone(), ctx);
                                     }
                                     continue;
                                 }
@@ -262,7 +618,7 @@ impl App for ShahApp {
                                 db_col.show(ui, |ui| {
                                     for (title, p) in pfs {
                                         if ui.button(title).clicked() {
-                                            self.add_database(p.clone());
+                                            self.add_database(p.clone(), ctx);
                                         }
                                     }
                                 });
@@ -276,12 +632,30 @@ impl App for ShahApp {
         if let Some(paths) = self.file_dialog.take_picked_multiple() {
             self.add_db_paths(paths);
         }
+        self.workspace_dialog.update(ctx);
+        if let Some(path) = self.workspace_dialog.take_picked() {
+            match self.workspace_dialog.mode() {
+                efd::DialogMode::SaveFile => self.save_workspace(path, ctx),
+                _ => self.open_workspace(path, ctx),
+            }
+        }
 
         // egui::CentralPanel::default().show(ctx, |ui| {
         //     ui.group(|ui| {});
         // });
 
+        self.set_known(ctx);
         egui::CentralPanel::default()
             .show(ctx, |ui| self.tree.ui(&mut self.behavior, ui));
+
+        for req in nav::take(ctx) {
+            match req {
+                nav::Request::Pick(gene) => self.pick_gene(gene, ctx),
+                nav::Request::Open(path, id) => self.open_gene(path, id, ctx),
+                nav::Request::Check(path) => self.open_report(path),
+            }
+        }
+        self.show_gene_pick(ctx);
+        self.dropped_files(ctx);
     }
 }
@@ -0,0 +1,423 @@
+//! integrity checks of entity files. the checks only read the file and
+//! report what they find, nothing is repaired
+
+use crate::db::nav;
+use shah::db::entity::{EntityHead, EntityKochProg, ENTITY_META};
+use shah::models::{Binary, Gene, Schema, SchemaModel};
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+/// how many items are read from disk at once
+const BATCH: u64 = 4096;
+/// issues past this are only counted
+const MAX_ISSUES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub level: Level,
+    /// the item the issue was found in, None for file wide issues
+    pub id: Option<u64>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub path: PathBuf,
+    pub name: String,
+    pub item_size: u64,
+    pub item_total: u64,
+    pub issues: Vec<Issue>,
+    /// issues that were found after MAX_ISSUES
+    pub hidden: u64,
+}
+
+impl Report {
+    fn push(&mut self, level: Level, id: Option<u64>, message: String) {
+        if self.issues.len() >= MAX_ISSUES {
+            self.hidden += 1;
+            return;
+        }
+        self.issues.push(Issue { level, id, message });
+    }
+
+    pub fn errors(&self) -> usize {
+        self.issues.iter().filter(|i| i.level == Level::Error).count()
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.issues.iter().filter(|i| i.level == Level::Warn).count()
+    }
+
+    /// a plain text report, one issue per line
+    pub fn write_text(&self, out: &mut String) {
+        let _ = writeln!(
+            out,
+            "{} ({}): {} items of {} bytes | {} errors, {} warnings",
+            self.path.display(),
+            self.name,
+            self.item_total,
+            self.item_size,
+            self.errors(),
+            self.warnings()
+        );
+        for i in self.issues.iter() {
+            let level = match i.level {
+                Level::Warn => "warn",
+                Level::Error => "error",
+            };
+            let _ = match i.id {
+                Some(id) => writeln!(out, "{level} #{id}: {}", i.message),
+                None => writeln!(out, "{level}: {}", i.message),
+            };
+        }
+        if self.hidden > 0 {
+            let _ = writeln!(out, "... and {} more", self.hidden);
+        }
+    }
+}
+
+/// an entity database that genes may point into
+struct Target {
+    file: File,
+    item_size: u64,
+    total: u64,
+    offset: u64,
+}
+
+impl Target {
+    fn open(path: &Path) -> Option<Self> {
+        let (mut file, head, model) = nav::open_entity(path)?;
+        let offset = nav::gene_offset(&model)? as u64;
+        let len = file.seek(SeekFrom::End(0)).ok()?;
+        let total = len.saturating_sub(ENTITY_META) / head.item_size;
+        Some(Self { file, item_size: head.item_size, total, offset })
+    }
+
+    /// Some(true) if the gene matches its item, Some(false) if only the
+    /// iter differs, None if this is not the target of the gene
+    fn find(&self, gene: &Gene) -> Option<bool> {
+        if gene.id.0 >= self.total {
+            return None;
+        }
+        let mut g = Gene::default();
+        let pos = ENTITY_META + gene.id.0 * self.item_size + self.offset;
+        self.file.read_exact_at(g.as_binary_mut(), pos).ok()?;
+        if g.id != gene.id || g.pepper != gene.pepper {
+            return None;
+        }
+        Some(g.iter == gene.iter)
+    }
+}
+
+/// (name, offset, length) of every string array inside a value of `schema`
+fn str_fields(
+    schema: &Schema, name: &str, offset: usize,
+    out: &mut Vec<(String, usize, usize)>,
+) {
+    let join = |n: &str| {
+        if name.is_empty() {
+            n.to_string()
+        } else {
+            format!("{name}.{n}")
+        }
+    };
+    match schema {
+        Schema::Array { is_str: true, length, .. } => {
+            out.push((name.to_string(), offset, *length as usize))
+        }
+        Schema::Array { length, kind, .. } => {
+            for i in 0..*length as usize {
+                let n = format!("{name}[{i}]");
+                str_fields(kind, &n, offset + i * kind.size(), out);
+            }
+        }
+        Schema::Model(m) => {
+            let mut pos = offset;
+            for (n, s) in m.fields.iter() {
+                str_fields(s, &join(n), pos, out);
+                pos += s.size();
+            }
+        }
+        Schema::Tuple(list) => {
+            let mut pos = offset;
+            for (i, s) in list.iter().enumerate() {
+                str_fields(s, &join(&i.to_string()), pos, out);
+                pos += s.size();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// checks the entity file at `path`. genes in it are resolved against the
+/// entity databases in `known`. without any other database the genes that
+/// are not found in the file itself are only a warning
+pub fn check_entity(path: &Path, known: &[PathBuf]) -> Result<Report, String> {
+    let Some((mut file, head, model)) = nav::read_entity(path) else {
+        return Err("not an entity database with a model schema".to_string());
+    };
+
+    let len = file.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+    let mut report = Report {
+        path: path.to_path_buf(),
+        name: head.db_head.name().to_string(),
+        item_size: head.item_size,
+        item_total: len.saturating_sub(ENTITY_META) / head.item_size,
+        issues: Vec::new(),
+        hidden: 0,
+    };
+
+    check_head(&file, &head, &model, len, &mut report);
+    if head.item_size != model.size {
+        // items can not be decoded with this schema
+        return Ok(report);
+    }
+
+    let own = nav::gene_offset(&model);
+    let schema = Schema::Model(model);
+    let mut genes = Vec::new();
+    nav::gene_fields(&schema, "", 0, &mut genes);
+    genes.retain(|(_, o)| Some(*o) != own);
+    let mut strs = Vec::new();
+    str_fields(&schema, "", 0, &mut strs);
+
+    let targets = known
+        .iter()
+        .chain([&path.to_path_buf()])
+        .filter_map(|p| Target::open(p))
+        .collect::<Vec<_>>();
+    let others = known.iter().any(|p| p != path);
+
+    let item_size = head.item_size;
+    let total = report.item_total;
+    let mut buf = vec![0u8; (BATCH * item_size) as usize];
+    let mut g = Gene::default();
+    let mut id = 0u64;
+    while id < total {
+        let n = BATCH.min(total - id);
+        let buf = &mut buf[..(n * item_size) as usize];
+        if let Err(e) = file.read_exact_at(buf, ENTITY_META + id * item_size) {
+            let msg = format!("could not read items from {id}: {e}");
+            report.push(Level::Error, None, msg);
+            break;
+        }
+        for (i, item) in buf.chunks(item_size as usize).enumerate() {
+            let item_id = id + i as u64;
+            if let Some(o) = own {
+                g.as_binary_mut().copy_from_slice(&item[o..o + Gene::S]);
+                if g.is_some() && g.id.0 != item_id {
+                    let msg = format!("gene id {} is not its position", g.id);
+                    report.push(Level::Error, Some(item_id), msg);
+                }
+            }
+            for (name, o) in genes.iter() {
+                g.as_binary_mut().copy_from_slice(&item[*o..*o + Gene::S]);
+                if g.is_none() {
+                    continue;
+                }
+                match targets.iter().find_map(|t| t.find(&g)) {
+                    Some(true) => {}
+                    Some(false) => {
+                        let msg = format!("{name}: stale gene {}", g.id);
+                        report.push(Level::Warn, Some(item_id), msg);
+                    }
+                    None if others => {
+                        let msg = format!(
+                            "{name}: gene {} points to no known item",
+                            g.id
+                        );
+                        report.push(Level::Error, Some(item_id), msg);
+                    }
+                    None => {
+                        let msg =
+                            format!("{name}: gene {} target not checked", g.id);
+                        report.push(Level::Warn, Some(item_id), msg);
+                    }
+                }
+            }
+            for (name, o, len) in strs.iter() {
+                let v = &item[*o..*o + *len];
+                let v = v.splitn(2, |x| *x == 0).next().unwrap_or_default();
+                if let Err(e) = core::str::from_utf8(v) {
+                    let msg = format!(
+                        "{name}: invalid utf-8 at byte {}",
+                        e.valid_up_to()
+                    );
+                    report.push(Level::Error, Some(item_id), msg);
+                }
+            }
+        }
+        id += n;
+    }
+
+    Ok(report)
+}
+
+/// checks that do not need the items
+fn check_head(
+    file: &File, head: &EntityHead, model: &SchemaModel, len: u64,
+    report: &mut Report,
+) {
+    if len < ENTITY_META {
+        let msg = format!("file is {len} bytes, the head is {ENTITY_META}");
+        report.push(Level::Error, None, msg);
+        return;
+    }
+    let tail = (len - ENTITY_META) % head.item_size;
+    if tail != 0 {
+        let msg = format!("truncated tail: {tail} bytes after the last item");
+        report.push(Level::Error, None, msg);
+    }
+
+    let fields = model.fields.iter().map(|(_, s)| s.size()).sum::<usize>();
+    if head.item_size != model.size {
+        let msg = format!(
+            "head item size is {} but the schema is {} bytes",
+            head.item_size, model.size
+        );
+        report.push(Level::Error, None, msg);
+    }
+    if fields as u64 != model.size {
+        let msg = format!(
+            "schema size is {} but its fields add up to {fields}",
+            model.size
+        );
+        report.push(Level::Error, None, msg);
+    }
+
+    let mut koch = EntityKochProg::default();
+    if file.read_exact_at(koch.as_binary_mut(), EntityHead::N).is_ok()
+        && koch.prog.0 > koch.total.0
+    {
+        let msg = format!(
+            "koch prog {} is past its total {}",
+            koch.prog.0, koch.total.0
+        );
+        report.push(Level::Warn, None, msg);
+    }
+}
+
+/// checks every file in `paths` against each other and prints the reports.
+/// the exit code is 1 if any file has errors
+pub fn run(paths: &[PathBuf]) -> i32 {
+    let mut code = 0;
+    for path in paths {
+        match check_entity(path, paths) {
+            Ok(report) => {
+                let mut out = String::new();
+                report.write_text(&mut out);
+                print!("{out}");
+                if report.errors() > 0 {
+                    code = 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                code = 1;
+            }
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn stale_genes() {
+        let fx = fixtures::get();
+        let report = check_entity(&fx.item, &[]).unwrap();
+        assert_eq!(report.item_total, fixtures::ITEMS);
+        assert_eq!(report.errors(), 0);
+        // every item past the first points to item 1 with an old iter
+        let ids = report.issues.iter().map(|i| i.id).collect::<Vec<_>>();
+        assert_eq!(ids, [Some(1), Some(2), Some(3), Some(4)]);
+        assert_eq!(report.issues[0].message, "friends[1]: stale gene 1");
+    }
+
+    #[test]
+    fn unchecked_target() {
+        let fx = fixtures::get();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("item.0.shah");
+        std::fs::copy(&fx.item, &path).unwrap();
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        let size = size_of::<fixtures::Item>() as u64;
+        let pos = ENTITY_META + size + 2 * Gene::S as u64;
+        file.write_all_at(fixtures::gene(50).as_binary(), pos).unwrap();
+
+        let report = check_entity(&path, &[]).unwrap();
+        assert_eq!(report.errors(), 0);
+        let issue = &report.issues[0];
+        assert_eq!((issue.level, issue.id), (Level::Warn, Some(1)));
+        assert_eq!(issue.message, "friends[0]: gene 50 target not checked");
+
+        let report =
+            check_entity(&path, std::slice::from_ref(&fx.item)).unwrap();
+        assert_eq!(report.errors(), 1);
+        assert_eq!(
+            report.issues[0].message,
+            "friends[0]: gene 50 points to no known item"
+        );
+    }
+
+    #[test]
+    fn small_item_size() {
+        let fx = fixtures::get();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("item.0.shah");
+        std::fs::copy(&fx.item, &path).unwrap();
+        let mut file = std::fs::OpenOptions::new();
+        let file = file.read(true).write(true).open(&path).unwrap();
+        let mut head = EntityHead::default();
+        file.read_exact_at(head.as_binary_mut(), 0).unwrap();
+        head.item_size = 8;
+        file.write_all_at(head.as_binary(), 0).unwrap();
+
+        assert!(nav::open_entity(&path).is_none());
+        let report = check_entity(&path, &[]).unwrap();
+        let size = size_of::<fixtures::Item>();
+        assert_eq!(
+            report.issues[0].message,
+            format!("head item size is 8 but the schema is {size} bytes")
+        );
+        // the genes of the item file are resolved without the broken copy
+        let known = [path, fx.item.clone()];
+        let report = check_entity(&fx.item, &known).unwrap();
+        assert_eq!(report.errors(), 0);
+    }
+
+    #[test]
+    fn truncated_tail() {
+        let fx = fixtures::get();
+        let report = check_entity(&fx.cut, &[]).unwrap();
+        assert_eq!(report.errors(), 1);
+        let issue = &report.issues[0];
+        assert_eq!((issue.level, issue.id), (Level::Error, None));
+        assert_eq!(
+            issue.message,
+            format!(
+                "truncated tail: {} bytes after the last item",
+                fixtures::CUT_TAIL
+            )
+        );
+    }
+
+    #[test]
+    fn not_an_entity() {
+        let fx = fixtures::get();
+        assert!(check_entity(&fx.trie, &[]).is_err());
+        assert!(check_entity(&fx.data.join("missing.shah"), &[]).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! the command line. the gui is only started without a subcommand
+
+use crate::check;
+use crate::db::{Database, DumpFormat, EntityDb, TrieConstDb};
+use clap::{Parser, Subcommand};
+use shah::models::{Binary, DbHead, ShahMagicDb};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "a viewer for shah databases",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    /// database files to open and directories to list in the viewer
+    pub paths: Vec<PathBuf>,
+    /// a workspace file to open in the viewer
+    #[arg(long, short)]
+    pub workspace: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// print the fields of an entity database with their byte ranges
+    Schema { file: PathBuf },
+    /// print the items of an entity database
+    Dump {
+        file: PathBuf,
+        /// id of the first item
+        #[arg(long, default_value_t = 0)]
+        skip: u64,
+        /// how many items are printed, every item after skip by default
+        #[arg(long)]
+        limit: Option<u64>,
+        /// comma separated field names like `name,address.city`
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+        #[arg(long, value_enum, default_value_t = DumpFormat::Table)]
+        format: DumpFormat,
+    },
+    /// print the head of a database and a summary of every field, or the
+    /// occupancy and fan-out of a trie const
+    Stats { file: PathBuf },
+    /// check entity files for integrity issues, the files are checked
+    /// against each other for gene references
+    Check {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+fn open_entity(path: PathBuf) -> Result<EntityDb, String> {
+    EntityDb::init(path.clone())
+        .map_err(|e| format!("could not open {path:?}: {e:?}"))
+}
+
+impl Command {
+    /// runs the command and returns the exit code
+    pub fn run(self) -> i32 {
+        let mut out = BufWriter::new(std::io::stdout().lock());
+        let res = match self {
+            Self::Check { files } => return check::run(&files),
+            Self::Schema { file } => open_entity(file).and_then(|db| {
+                db.write_schema(&mut out).map_err(|e| e.to_string())
+            }),
+            Self::Dump { file, skip, limit, fields, format } => {
+                open_entity(file).and_then(|db| {
+                    db.dump(skip, limit, &fields, format, &mut out)
+                })
+            }
+            Self::Stats { file } => stats(file, &mut out),
+        };
+        match res.and_then(|_| out.flush().map_err(|e| e.to_string())) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("{e}");
+                1
+            }
+        }
+    }
+}
+
+fn stats(path: PathBuf, out: &mut impl Write) -> Result<(), String> {
+    let file = File::open(&path).map_err(|e| format!("{path:?}: {e}"))?;
+    let mut head = DbHead::default();
+    file.read_exact_at(head.as_binary_mut(), 0)
+        .map_err(|e| format!("could not read the head of {path:?}: {e}"))?;
+    if !head.magic.is_valid() {
+        return Err(format!("{path:?} is not a shah database"));
+    }
+    match head.magic.db() {
+        ShahMagicDb::Entity => return open_entity(path)?.write_stats(out),
+        ShahMagicDb::TrieConst => {
+            let db = TrieConstDb::init(path.clone())
+                .map_err(|e| format!("could not open {path:?}: {e:?}"))?;
+            return db.write_stats(out);
+        }
+        _ => {}
+    }
+
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    let io = |e: std::io::Error| e.to_string();
+    writeln!(out, "db: {}.{}", head.name(), head.revision).map_err(io)?;
+    writeln!(out, "kind: {:?}", head.magic.db()).map_err(io)?;
+    writeln!(out, "size: {len} bytes").map_err(io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_or_command() {
+        let cli = Cli::parse_from(["shah-visual", "a.shah", "data", "-w", "x"]);
+        assert_eq!(cli.paths, [PathBuf::from("a.shah"), "data".into()]);
+        assert_eq!(cli.workspace, Some("x".into()));
+        assert!(cli.command.is_none());
+
+        let cli = Cli::parse_from(["shah-visual", "stats", "a.shah"]);
+        assert!(cli.paths.is_empty());
+        assert!(matches!(cli.command, Some(Command::Stats { .. })));
+    }
+
+    #[test]
+    fn trie_const_stats() {
+        let mut out = Vec::new();
+        stats(crate::fixtures::get().trie.clone(), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let expect = [
+            "db: shop/names",
+            "abc: 4 chars | cache: 2 chars | index: 2 levels",
+            "cache: 3/16 slots used (18.8%) | 13 empty",
+            "index: 7 nodes | 4 leaves | 4 keys",
+            "space: 4664 bytes | nodes 352 | unused positions 256 (72.7%) | \
+             unreachable 0",
+            "level 1 (under 2 chars): 3 nodes",
+            "    1: 2",
+            "    2: 1",
+            "level 2 (under 3 chars): 4 nodes",
+            "    1: 4",
+        ];
+        assert_eq!(text.lines().collect::<Vec<_>>(), expect);
+    }
+
+    #[test]
+    fn small_item_size() {
+        use shah::db::entity::EntityHead;
+        use std::fs::OpenOptions;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("item.0.shah");
+        std::fs::copy(&crate::fixtures::get().item, &path).unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&path);
+        let file = file.unwrap();
+        let mut head = EntityHead::default();
+        file.read_exact_at(head.as_binary_mut(), 0).unwrap();
+        head.item_size = 8;
+        file.write_all_at(head.as_binary(), 0).unwrap();
+
+        let err = stats(path.clone(), &mut Vec::new()).unwrap_err();
+        assert!(err.starts_with("could not open"), "{err}");
+        let dump = Command::Dump {
+            file: path,
+            skip: 0,
+            limit: None,
+            fields: Vec::new(),
+            format: DumpFormat::Table,
+        };
+        assert_eq!(dump.run(), 1);
+    }
+}
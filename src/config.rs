@@ -5,11 +5,14 @@ use std::{fs::read_to_string, path::PathBuf, sync::OnceLock};
 #[serde(default)]
 struct ConfigToml {
     quick_access: toml::Table,
+    decoders: toml::Table,
 }
 
 #[derive(Debug, Default)]
 pub struct Config {
     pub quick_access: Vec<(String, PathBuf)>,
+    /// custom magic `(prefix, db)` mapped to a builtin decoder name
+    pub decoders: Vec<((u8, u16), String)>,
 }
 
 pub fn config() -> &'static Config {
@@ -32,5 +35,22 @@ pub fn config() -> &'static Config {
         }
     }
 
+    for (key, val) in conf.decoders.iter() {
+        let Some((prefix, db)) = key.split_once('-') else {
+            panic!("invalid decoders key: {key}. use \"prefix-db\"");
+        };
+        let (Ok(prefix), Ok(db)) = (prefix.parse(), db.parse()) else {
+            panic!(
+                "invalid decoders key: {key}. prefix and db must be numbers"
+            );
+        };
+        match val {
+            toml::Value::String(s) => {
+                config.decoders.push(((prefix, db), s.to_string()));
+            }
+            _ => panic!("invalid decoders. only strings are valid."),
+        }
+    }
+
     STATE.get_or_init(|| config)
 }
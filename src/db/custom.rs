@@ -0,0 +1,82 @@
+use super::entity::EntityDb;
+use super::raw::RawDb;
+use super::trie::TrieDb;
+use super::trie_const::TrieConstDb;
+use super::{Database, TileState};
+use crate::Result;
+use shah::models::ShahMagic;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+
+/// object safe view of a [`Database`] opened through the registry. it is
+/// sent back from the thread that opens it
+pub trait CustomDb: Send {
+    fn title(&self) -> String;
+    fn show(&mut self, ui: &mut egui::Ui);
+    fn reload(&mut self);
+    fn state(&self) -> TileState;
+    fn restore(&mut self, state: &TileState, ctx: &egui::Context);
+}
+
+impl<T: Database + Send> CustomDb for T {
+    fn title(&self) -> String {
+        Database::title(self)
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        Database::show(self, ui)
+    }
+
+    fn reload(&mut self) {
+        Database::reload(self)
+    }
+
+    fn state(&self) -> TileState {
+        Database::state(self)
+    }
+
+    fn restore(&mut self, state: &TileState, ctx: &egui::Context) {
+        Database::restore(self, state, ctx)
+    }
+}
+
+pub type Decoder = fn(path: PathBuf) -> Result<Box<dyn CustomDb>>;
+
+/// custom magic prefix and db value
+type MagicKey = (u8, u16);
+
+fn registry() -> &'static RwLock<HashMap<MagicKey, Decoder>> {
+    static STATE: OnceLock<RwLock<HashMap<MagicKey, Decoder>>> =
+        OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+pub fn decoder<T: Database + Send + 'static>(
+    path: PathBuf,
+) -> Result<Box<dyn CustomDb>> {
+    Ok(Box::new(T::init(path)?))
+}
+
+/// decoders that can be assigned to a custom magic from config.toml
+pub fn builtin(name: &str) -> Option<Decoder> {
+    Some(match name {
+        "entity" => decoder::<EntityDb>,
+        "trie_const" => decoder::<TrieConstDb>,
+        "trie" => decoder::<TrieDb>,
+        "raw" => decoder::<RawDb>,
+        _ => return None,
+    })
+}
+
+/// registers a decoder for every database with the given custom magic.
+/// registering the same magic twice replaces the old decoder
+pub fn register(prefix: u8, db: u16, decoder: Decoder) {
+    let mut reg = registry().write().expect("decoder registry poisoned");
+    reg.insert((prefix, db), decoder);
+}
+
+pub fn find(magic: &ShahMagic) -> Option<Decoder> {
+    let reg = registry().read().expect("decoder registry poisoned");
+    reg.get(&(magic.prefix(), magic.raw_db())).copied()
+}
@@ -0,0 +1,271 @@
+//! headless access to an entity database for the command line. values are
+//! written in the same text forms as the export
+
+use super::export::{Column, Format, Writer};
+use super::field::{schema_at, Field, Kind, Prim};
+use super::EntityDb;
+use crate::value::{Decoded, NumberStats};
+use shah::db::entity::ENTITY_META;
+use std::io::Write;
+use std::os::unix::fs::FileExt;
+
+/// how many items are read from disk at once
+const DUMP_BATCH: u64 = 4096;
+/// table rows that are read before the column widths are fixed
+const TABLE_SAMPLE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum DumpFormat {
+    /// one json object per line
+    Json,
+    Csv,
+    /// aligned columns, the widths fit the first rows
+    Table,
+}
+
+impl EntityDb {
+    /// the columns of `keys`, or of every field when `keys` is empty
+    fn columns(&self, keys: &[String]) -> Result<Vec<Column>, String> {
+        let col = |f: &Field| (f.key.clone(), f.path.clone(), f.range.clone());
+        if keys.is_empty() {
+            return Ok(self.fields.iter().map(col).collect());
+        }
+        keys.iter()
+            .map(|k| match self.fields.iter().find(|f| &f.key == k) {
+                Some(f) => Ok(col(f)),
+                None => Err(format!(
+                    "unknown field {k:?}, the fields are: {}",
+                    self.fields
+                        .iter()
+                        .map(|f| f.key.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            })
+            .collect()
+    }
+
+    /// calls `row` for the items in `skip..skip + limit`
+    fn each_item(
+        &self, skip: u64, limit: Option<u64>,
+        mut row: impl FnMut(u64, &[u8]) -> std::io::Result<()>,
+    ) -> Result<(), String> {
+        let end = match limit {
+            Some(n) => skip.saturating_add(n).min(self.item_total),
+            None => self.item_total,
+        };
+        let size = self.item_size as usize;
+        let mut buf = vec![0u8; DUMP_BATCH as usize * size];
+        let mut id = skip;
+        while id < end {
+            let n = DUMP_BATCH.min(end - id);
+            let buf = &mut buf[..n as usize * size];
+            self.file
+                .read_exact_at(buf, ENTITY_META + id * self.item_size)
+                .map_err(|e| format!("could not read items from {id}: {e}"))?;
+            for (i, item) in buf.chunks(size).enumerate() {
+                row(id + i as u64, item).map_err(|e| e.to_string())?;
+            }
+            id += n;
+        }
+        Ok(())
+    }
+
+    pub fn write_schema(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "<{} {} /> of {}.{}",
+            self.schema.name, self.schema.size, self.name, self.revision
+        )?;
+        for f in self.fields.iter() {
+            writeln!(
+                out,
+                "{:>6}..{:<6} {}",
+                f.range.start, f.range.end, f.name
+            )?;
+        }
+        Ok(())
+    }
+
+    /// writes the items in `skip..skip + limit` with the fields in `keys`
+    pub fn dump(
+        &self, skip: u64, limit: Option<u64>, keys: &[String],
+        format: DumpFormat, out: &mut impl Write,
+    ) -> Result<(), String> {
+        let cols = self.columns(keys)?;
+        let format = match format {
+            DumpFormat::Json => Format::Jsonl,
+            DumpFormat::Csv => Format::Csv,
+            DumpFormat::Table => {
+                return self.dump_table(skip, limit, cols, out)
+            }
+        };
+
+        let writer = Writer { format, model: &self.schema, cols };
+        let mut text = String::new();
+        writer.header(&mut text);
+        self.each_item(skip, limit, |id, item| {
+            writer.row(id, item, &mut text);
+            if text.len() > 1 << 16 {
+                out.write_all(text.as_bytes())?;
+                text.clear();
+            }
+            Ok(())
+        })?;
+        out.write_all(text.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    /// the column widths are taken from the header and the first
+    /// TABLE_SAMPLE rows, the rest is written as it is read
+    fn dump_table<W: Write>(
+        &self, skip: u64, limit: Option<u64>, cols: Vec<Column>, out: &mut W,
+    ) -> Result<(), String> {
+        let mut rows = vec![std::iter::once("id".to_string())
+            .chain(cols.iter().map(|(k, _, _)| k.clone()))
+            .collect::<Vec<_>>()];
+        let mut widths = Vec::new();
+        let mut line = String::new();
+        let mut write_row = |widths: &[usize], row: &[String], out: &mut W| {
+            line.clear();
+            for (i, (w, cell)) in widths.iter().zip(row).enumerate() {
+                if i > 0 {
+                    line.push_str(" | ");
+                }
+                line.push_str(cell);
+                let pad = w.saturating_sub(cell.chars().count());
+                line.extend(std::iter::repeat_n(' ', pad));
+            }
+            writeln!(out, "{}", line.trim_end())
+        };
+        let fit = |rows: &[Vec<String>]| {
+            let mut widths = vec![0; cols.len() + 1];
+            for row in rows.iter() {
+                for (w, cell) in widths.iter_mut().zip(row) {
+                    *w = (*w).max(cell.chars().count());
+                }
+            }
+            widths
+        };
+
+        self.each_item(skip, limit, |id, item| {
+            let mut row = vec![id.to_string()];
+            for (_, path, range) in cols.iter() {
+                let mut cell = String::new();
+                if let Some(s) = schema_at(&self.schema, path) {
+                    Decoded::decode(s, &item[range.clone()])
+                        .write_text(&mut cell);
+                }
+                row.push(cell);
+            }
+            if !widths.is_empty() {
+                return write_row(&widths, &row, out);
+            }
+            rows.push(row);
+            if rows.len() > TABLE_SAMPLE {
+                widths = fit(&rows);
+                for row in rows.drain(..) {
+                    write_row(&widths, &row, out)?;
+                }
+            }
+            Ok(())
+        })?;
+
+        if widths.is_empty() {
+            widths = fit(&rows);
+        }
+        for row in rows.iter() {
+            write_row(&widths, row, out).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// the head of the database and a summary of every field
+    pub fn write_stats(&self, out: &mut impl Write) -> Result<(), String> {
+        // numbers get their range, everything else how often it is set
+        let mut numbers = vec![NumberStats::default(); self.fields.len()];
+        let mut set = vec![0u64; self.fields.len()];
+        self.each_item(0, None, |_, item| {
+            for (i, f) in self.fields.iter().enumerate() {
+                let v = &item[f.range.clone()];
+                if f.is_number() {
+                    numbers[i].add(&f.decode(v));
+                } else {
+                    set[i] += v.iter().any(|x| *x != 0) as u64;
+                }
+            }
+            Ok(())
+        })?;
+
+        let io = |e: std::io::Error| e.to_string();
+        let tail = self
+            .file
+            .metadata()
+            .map(|m| m.len().saturating_sub(ENTITY_META) % self.item_size)
+            .map_err(io)?;
+        writeln!(out, "db: {}.{}", self.name, self.revision).map_err(io)?;
+        writeln!(out, "model: <{} {} />", self.schema.name, self.item_size)
+            .map_err(io)?;
+        writeln!(out, "items: {}", self.item_total).map_err(io)?;
+        writeln!(out, "trailing bytes: {tail}").map_err(io)?;
+        writeln!(
+            out,
+            "koch prog: {}/{}",
+            self.koch_prog.prog, self.koch_prog.total
+        )
+        .map_err(io)?;
+
+        for (i, f) in self.fields.iter().enumerate() {
+            let text = if f.is_number() {
+                numbers[i].to_string()
+            } else if f.kind == Kind::Prim(Prim::Bool) {
+                format!("{} true", set[i])
+            } else {
+                format!("{} set", set[i])
+            };
+            writeln!(out, "{}: {text}", f.name).map_err(io)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn dump(keys: &[&str], format: DumpFormat) -> Result<String, String> {
+        let db = EntityDb::init(fixtures::get().item.clone()).unwrap();
+        let keys = keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let mut out = Vec::new();
+        db.dump(1, Some(2), &keys, format, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn formats() {
+        let keys = ["name", "score", "pos.y", "owner"];
+        assert_eq!(
+            dump(&keys, DumpFormat::Csv).unwrap(),
+            "id,name,score,pos.y,owner\n\
+             1,apple,0.25,-1,\"Gene(0, 0, [0, 0, 0], 0)\"\n\
+             2,bread,0.5,-2,\"Gene(1, 0, [1, 2, 3], 1)\"\n"
+        );
+        assert_eq!(
+            dump(&keys[..2], DumpFormat::Json).unwrap(),
+            "{\"id\": 1, \"name\": \"apple\", \"score\": 0.25}\n\
+             {\"id\": 2, \"name\": \"bread\", \"score\": 0.5}\n"
+        );
+        assert_eq!(
+            dump(&keys[..3], DumpFormat::Table).unwrap(),
+            "id | name  | score | pos.y\n\
+             1  | apple | 0.25  | -1\n\
+             2  | bread | 0.5   | -2\n"
+        );
+    }
+
+    #[test]
+    fn unknown_field() {
+        let err = dump(&["nope"], DumpFormat::Csv).unwrap_err();
+        assert!(err.starts_with("unknown field \"nope\""), "{err}");
+    }
+}
@@ -0,0 +1,355 @@
+use super::field::{Kind, Prim};
+use super::EntityDb;
+use crate::value::decode_str;
+use shah::db::entity::ENTITY_META;
+use shah::models::{Binary, Gene};
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::str::FromStr;
+
+/// previous bytes of an item that was written back to the file. kept on
+/// the database so leaving edit mode does not lose what can be undone
+pub struct Journal {
+    id: u64,
+    before: Vec<u8>,
+}
+
+/// state of the opt-in edit mode. the file is only opened for writing
+/// while this exists
+pub struct EditMode {
+    file: File,
+    /// item id and the edited copy of its bytes
+    draft: Option<(u64, Vec<u8>)>,
+    error: Option<String>,
+}
+
+impl EditMode {
+    /// the bytes of an item as they are on disk right now
+    fn read(&mut self, id: u64, item_size: u64) -> Option<Vec<u8>> {
+        let pos = ENTITY_META + id * item_size;
+        let mut data = vec![0; item_size as usize];
+        match self.file.read_exact_at(&mut data, pos) {
+            Ok(()) => Some(data),
+            Err(e) => {
+                log::error!("could not read item {id} at {pos}: {e}");
+                self.error = Some(format!("read failed: {e}"));
+                None
+            }
+        }
+    }
+
+    fn write(&mut self, id: u64, item_size: u64, data: &[u8]) -> bool {
+        let pos = ENTITY_META + id * item_size;
+        match self.file.write_all_at(data, pos) {
+            Ok(()) => {
+                self.error = None;
+                true
+            }
+            Err(e) => {
+                log::error!("could not write item {id} at {pos}: {e}");
+                self.error = Some(format!("write failed: {e}"));
+                false
+            }
+        }
+    }
+}
+
+/// exact text editor for 64 bit integers which do not fit a DragValue
+fn edit_parse<T: FromStr + ToString>(value: &mut T, ui: &mut egui::Ui) -> bool {
+    let id = ui.next_auto_id();
+    let mut text = ui
+        .data(|d| d.get_temp::<String>(id))
+        .unwrap_or_else(|| value.to_string());
+    let valid = text.parse::<T>().is_ok();
+    let mut edit = egui::TextEdit::singleline(&mut text).id(id);
+    if !valid {
+        edit = edit.text_color(ui.visuals().error_fg_color);
+    }
+    let res = ui.add(edit.desired_width(180.0));
+    let mut changed = false;
+    if res.changed() {
+        if let Ok(v) = text.parse() {
+            *value = v;
+            changed = true;
+        }
+    }
+    if res.has_focus() {
+        ui.data_mut(|d| d.insert_temp(id, text));
+    } else {
+        ui.data_mut(|d| d.remove::<String>(id));
+    }
+    changed
+}
+
+fn edit_gene(v: &mut [u8], ui: &mut egui::Ui) -> bool {
+    let mut g = Gene::default();
+    g.as_binary_mut().copy_from_slice(v);
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("id:");
+        changed |= edit_parse(&mut g.id.0, ui);
+        ui.label("iter:");
+        changed |= ui.add(egui::DragValue::new(&mut g.iter)).changed();
+        ui.label("pepper:");
+        for p in g.pepper.iter_mut() {
+            changed |= ui
+                .add(egui::DragValue::new(p).hexadecimal(2, false, false))
+                .changed();
+        }
+        ui.label("server:");
+        changed |= ui.add(egui::DragValue::new(&mut g.server)).changed();
+    });
+    if changed {
+        v.copy_from_slice(g.as_binary());
+    }
+    changed
+}
+
+fn edit_prim(p: Prim, v: &mut [u8], ui: &mut egui::Ui) -> bool {
+    macro_rules! drag {
+        ($ty:ty) => {{
+            let mut x = <$ty>::from_le_bytes((&*v).try_into().unwrap());
+            let changed = ui.add(egui::DragValue::new(&mut x)).changed();
+            v.copy_from_slice(&x.to_le_bytes());
+            changed
+        }};
+    }
+    macro_rules! parse {
+        ($ty:ty) => {{
+            let mut x = <$ty>::from_le_bytes((&*v).try_into().unwrap());
+            let changed = edit_parse(&mut x, ui);
+            v.copy_from_slice(&x.to_le_bytes());
+            changed
+        }};
+    }
+    match p {
+        Prim::U8 => drag!(u8),
+        Prim::U16 => drag!(u16),
+        Prim::U32 => drag!(u32),
+        Prim::U64 => parse!(u64),
+        Prim::I8 => drag!(i8),
+        Prim::I16 => drag!(i16),
+        Prim::I32 => drag!(i32),
+        Prim::I64 => parse!(i64),
+        Prim::F32 => drag!(f32),
+        Prim::F64 => drag!(f64),
+        Prim::Bool => {
+            let mut x = v[0] != 0;
+            let changed = ui.checkbox(&mut x, "").changed();
+            v[0] = x as u8;
+            changed
+        }
+        Prim::Gene => edit_gene(v, ui),
+    }
+}
+
+/// the string is stored null terminated, so it may use the whole array
+fn edit_str(v: &mut [u8], ui: &mut egui::Ui) -> bool {
+    let mut text = decode_str(v).to_string();
+    let res = ui.add(
+        egui::TextEdit::singleline(&mut text)
+            .char_limit(v.len())
+            .hint_text(format!("max {} bytes", v.len())),
+    );
+    if !res.changed() {
+        return false;
+    }
+    let mut end = text.len().min(v.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    v.fill(0);
+    v[..end].copy_from_slice(&text.as_bytes()[..end]);
+    true
+}
+
+/// editor for one field. returns None for kinds without an editor
+fn edit_field(kind: Kind, v: &mut [u8], ui: &mut egui::Ui) -> Option<bool> {
+    Some(match kind {
+        Kind::Prim(p) => edit_prim(p, v, ui),
+        Kind::Str => edit_str(v, ui),
+        Kind::Array(p) => {
+            let mut changed = false;
+            egui::CollapsingHeader::new(format!(
+                "{} items",
+                v.len() / p.size()
+            ))
+            .id_salt(ui.next_auto_id())
+            .show(ui, |ui| {
+                for (i, c) in v.chunks_mut(p.size()).enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{i}:"));
+                        changed |= edit_prim(p, c, ui);
+                    });
+                }
+            });
+            changed
+        }
+        Kind::Other => return None,
+    })
+}
+
+impl EntityDb {
+    fn enable_edit(&mut self) {
+        let file = OpenOptions::new().read(true).write(true).open(&self.path);
+        match file {
+            Ok(file) => {
+                self.edit = Some(EditMode { file, draft: None, error: None });
+            }
+            Err(e) => {
+                log::error!("could not open {:?} for writing: {e}", self.path);
+                self.edit_error =
+                    Some(format!("could not open for writing: {e}"));
+            }
+        }
+    }
+
+    pub(super) fn show_edit_toggle(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if self.edit.is_none() {
+                if ui.button("edit mode").clicked() {
+                    self.edit_confirm = true;
+                }
+            } else {
+                ui.colored_label(ui.visuals().warn_fg_color, "editing");
+                if ui.button("leave edit mode").clicked() {
+                    self.edit = None;
+                }
+            }
+            if let Some(e) = &self.edit_error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+        });
+
+        if !self.edit_confirm {
+            return;
+        }
+        let modal = egui::Modal::new(self.id.with("edit confirm"));
+        let res = modal.show(ui.ctx(), |ui| {
+            ui.heading("enable edit mode?");
+            ui.label(format!(
+                "saved items are written directly into {:?}",
+                self.path
+            ));
+            ui.label("make sure nothing else is writing to this database");
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("enable").clicked() {
+                    self.enable_edit();
+                    self.edit_confirm = false;
+                }
+                if ui.button("cancel").clicked() {
+                    self.edit_confirm = false;
+                }
+            });
+        });
+        if res.should_close() {
+            self.edit_confirm = false;
+        }
+    }
+
+    /// form for the active item, shown instead of the read only view
+    pub(super) fn show_edit_item(&mut self, ui: &mut egui::Ui) {
+        let Some(edit) = &mut self.edit else { return };
+        let Some(active) = &mut self.active_item else { return };
+        let Some(item) = &mut active.data else { return };
+        let id = active.id;
+        if edit.draft.as_ref().is_none_or(|(did, _)| *did != id) {
+            edit.draft = Some((id, item.clone()));
+        }
+        let Some((_, draft)) = &mut edit.draft else { return };
+
+        ui.label(format!("editing item {id}"));
+        egui::ScrollArea::both().max_height(400.0).show(ui, |ui| {
+            egui::Grid::new(self.id.with("edit grid")).striped(true).show(
+                ui,
+                |ui| {
+                    for f in self.fields.iter() {
+                        ui.label(&f.name);
+                        let v = &mut draft[f.range.clone()];
+                        if edit_field(f.kind, v, ui).is_none() {
+                            f.show(v, ui);
+                        }
+                        ui.end_row();
+                    }
+                },
+            );
+        });
+
+        let dirty = draft != item;
+        let (mut save, mut revert) = (false, false);
+        ui.horizontal(|ui| {
+            save = ui.add_enabled(dirty, egui::Button::new("save")).clicked();
+            revert =
+                ui.add_enabled(dirty, egui::Button::new("revert")).clicked();
+        });
+        let draft = save.then(|| draft.clone());
+        if revert {
+            edit.draft = None;
+        }
+        let Some(draft) = draft else { return };
+        let Some(before) = edit.read(id, self.item_size) else { return };
+        if before != *item {
+            log::warn!("item {id} of {} changed on disk", self.name);
+            edit.error = Some(format!(
+                "item {id} changed on disk since it was read, \
+                 the view now shows the new bytes"
+            ));
+            self.pages.set(id, &before);
+            *item = before;
+            return;
+        }
+        if edit.write(id, self.item_size, &draft) {
+            self.journal.push(Journal { id, before });
+            log::info!("item {id} of {} was edited", self.name);
+            self.pages.set(id, &draft);
+            *item = draft;
+        }
+    }
+
+    pub(super) fn show_journal(&mut self, ui: &mut egui::Ui) {
+        if let Some(e) = self.edit.as_ref().and_then(|e| e.error.as_ref()) {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+        if self.journal.is_empty() {
+            return;
+        }
+
+        let mut undo = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(format!("journal: {} edits", self.journal.len()));
+            let Some(last) = self.journal.last() else { return };
+            if self.edit.is_some() {
+                undo = ui.button(format!("undo item {}", last.id)).clicked();
+            } else {
+                ui.weak("enter edit mode to undo");
+            }
+        });
+        egui::CollapsingHeader::new("journal")
+            .id_salt(self.id.with("journal"))
+            .show(ui, |ui| {
+                for (i, j) in self.journal.iter().enumerate().rev() {
+                    ui.label(format!("{}: item {}", i + 1, j.id));
+                }
+            });
+        if !undo {
+            return;
+        }
+
+        let Some(edit) = &mut self.edit else { return };
+        let Some(last) = self.journal.pop() else { return };
+        if !edit.write(last.id, self.item_size, &last.before) {
+            self.journal.push(last);
+            return;
+        }
+        if edit.draft.as_ref().is_some_and(|(id, _)| *id == last.id) {
+            edit.draft = None;
+        }
+        self.pages.set(last.id, &last.before);
+        if let Some(active) = &mut self.active_item {
+            if active.id == last.id {
+                active.data = Some(last.before);
+            }
+        }
+    }
+}
@@ -0,0 +1,431 @@
+//! export of entity rows to CSV, TSV and JSON Lines. values are written in
+//! the text forms of [`crate::value`]
+//!
+//! CSV is quoted per RFC 4180. TSV escapes tab, newline, carriage return
+//! and backslash as `\t`, `\n`, `\r` and `\\`, so every row is one line
+
+use super::field::schema_at;
+use super::EntityDb;
+use crate::value::{json_str, Decoded};
+use egui_file_dialog as efd;
+use shah::db::entity::{EntityHead, ENTITY_META};
+use shah::models::{Binary, Schema, SchemaModel};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+/// how many items are read from disk at once while exporting
+const EXPORT_BATCH: u64 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Tsv,
+    Jsonl,
+}
+
+impl Format {
+    const ALL: [Self; 3] = [Self::Csv, Self::Tsv, Self::Jsonl];
+
+    fn ext(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Tsv => "tsv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// which rows are exported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    /// the rows visible in the table
+    Page,
+    /// the filter result, in sorted order when a sort is active
+    Rows,
+    File,
+}
+
+fn write_cell(format: Format, cell: &str, out: &mut String) {
+    match format {
+        Format::Csv => {
+            if cell.contains([',', '"', '\n', '\r']) {
+                out.push('"');
+                out.push_str(&cell.replace('"', "\"\""));
+                out.push('"');
+            } else {
+                out.push_str(cell);
+            }
+        }
+        Format::Tsv => {
+            for c in cell.chars() {
+                match c {
+                    '\t' => out.push_str("\\t"),
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    '\\' => out.push_str("\\\\"),
+                    c => out.push(c),
+                }
+            }
+        }
+        Format::Jsonl => json_str(cell, out),
+    }
+}
+
+/// a column of the export: field name, schema path and range
+pub(super) type Column = (String, Vec<usize>, Range<usize>);
+
+pub(super) struct Writer<'a> {
+    pub format: Format,
+    pub model: &'a SchemaModel,
+    pub cols: Vec<Column>,
+}
+
+impl Writer<'_> {
+    fn sep(&self) -> char {
+        if self.format == Format::Tsv {
+            '\t'
+        } else {
+            ','
+        }
+    }
+
+    pub fn header(&self, out: &mut String) {
+        if self.format == Format::Jsonl {
+            return;
+        }
+        out.push_str("id");
+        for (name, _, _) in self.cols.iter() {
+            out.push(self.sep());
+            write_cell(self.format, name, out);
+        }
+        out.push('\n');
+    }
+
+    pub fn row(&self, id: u64, item: &[u8], out: &mut String) {
+        let mut cell = String::new();
+        if self.format == Format::Jsonl {
+            let _ = write!(out, "{{\"id\": {id}");
+            for (name, path, range) in self.cols.iter() {
+                out.push_str(", ");
+                json_str(name, out);
+                out.push_str(": ");
+                match schema_at(self.model, path) {
+                    Some(s) => {
+                        Decoded::decode(s, &item[range.clone()]).write_json(out)
+                    }
+                    None => out.push_str("null"),
+                }
+            }
+            out.push_str("}\n");
+            return;
+        }
+
+        let _ = write!(out, "{id}");
+        for (_, path, range) in self.cols.iter() {
+            out.push(self.sep());
+            cell.clear();
+            if let Some(s) = schema_at(self.model, path) {
+                Decoded::decode(s, &item[range.clone()]).write_text(&mut cell);
+            }
+            write_cell(self.format, &cell, out);
+        }
+        out.push('\n');
+    }
+}
+
+enum ExportMsg {
+    Progress(u64),
+    Error(String),
+    Canceled,
+}
+
+/// writes rows to a file on a background thread. the rows go to a
+/// `.part` file next to the destination, which is renamed once they are
+/// all written and removed if the export fails or is canceled
+pub struct ExportJob {
+    pub path: PathBuf,
+    pub written: u64,
+    pub total: u64,
+    pub error: Option<String>,
+    pub canceled: bool,
+    done: bool,
+    rx: Receiver<ExportMsg>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl ExportJob {
+    /// exports `ids`, or every item of the file when `ids` is None
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(
+        file: &File, path: PathBuf, format: Format, cols: Vec<Column>,
+        item_size: u64, total: u64, ids: Option<Vec<u64>>, ctx: egui::Context,
+    ) -> std::io::Result<Self> {
+        let file = file.try_clone()?;
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stop = cancel.clone();
+        let total = ids.as_ref().map(|v| v.len() as u64).unwrap_or(total);
+        let dest = path.clone();
+        let mut part = path.clone().into_os_string();
+        part.push(".part");
+        let part = PathBuf::from(part);
+
+        std::thread::spawn(move || {
+            // Ok(false) when canceled
+            let run = || -> Result<bool, String> {
+                let mut head = EntityHead::default();
+                file.read_exact_at(head.as_binary_mut(), 0)
+                    .map_err(|e| e.to_string())?;
+                let model = match Schema::decode(&head.schema) {
+                    Ok(Schema::Model(m)) => m,
+                    _ => return Err("invalid schema".to_string()),
+                };
+                let writer = Writer { format, model: &model, cols };
+                let out = File::create(&part).map_err(|e| e.to_string())?;
+                let mut out = BufWriter::new(out);
+
+                let size = item_size as usize;
+                let mut buf = vec![0u8; EXPORT_BATCH as usize * size];
+                let mut text = String::new();
+                writer.header(&mut text);
+                let mut done = 0u64;
+                while done < total {
+                    if stop.load(AtomicOrdering::Relaxed) {
+                        return Ok(false);
+                    }
+                    let n = EXPORT_BATCH.min(total - done);
+                    match &ids {
+                        Some(ids) => {
+                            let buf = &mut buf[..size];
+                            for id in &ids[done as usize..(done + n) as usize] {
+                                let pos = ENTITY_META + id * item_size;
+                                file.read_exact_at(buf, pos)
+                                    .map_err(|e| e.to_string())?;
+                                writer.row(*id, buf, &mut text);
+                            }
+                        }
+                        None => {
+                            let buf = &mut buf[..n as usize * size];
+                            let pos = ENTITY_META + done * item_size;
+                            file.read_exact_at(buf, pos)
+                                .map_err(|e| e.to_string())?;
+                            for (i, item) in buf.chunks(size).enumerate() {
+                                writer.row(done + i as u64, item, &mut text);
+                            }
+                        }
+                    }
+                    out.write_all(text.as_bytes())
+                        .map_err(|e| e.to_string())?;
+                    text.clear();
+                    done += n;
+                    let _ = tx.send(ExportMsg::Progress(done));
+                    ctx.request_repaint();
+                }
+                out.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+                out.flush().map_err(|e| e.to_string())?;
+                drop(out);
+                std::fs::rename(&part, &dest).map_err(|e| e.to_string())?;
+                Ok(true)
+            };
+            match run() {
+                Ok(true) => {}
+                Ok(false) => {
+                    let _ = std::fs::remove_file(&part);
+                    let _ = tx.send(ExportMsg::Canceled);
+                }
+                Err(e) => {
+                    log::error!("export to {dest:?} failed: {e}");
+                    let _ = std::fs::remove_file(&part);
+                    let _ = tx.send(ExportMsg::Error(e));
+                }
+            }
+            ctx.request_repaint();
+        });
+
+        Ok(Self {
+            path,
+            written: 0,
+            total,
+            error: None,
+            canceled: false,
+            done: false,
+            rx,
+            cancel,
+        })
+    }
+
+    pub fn poll(&mut self) {
+        loop {
+            match self.rx.try_recv() {
+                Ok(ExportMsg::Progress(n)) => self.written = n,
+                Ok(ExportMsg::Error(e)) => self.error = Some(e),
+                Ok(ExportMsg::Canceled) => self.canceled = true,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.done
+    }
+
+    /// stops the export, the partial file is removed
+    pub fn cancel(&self) {
+        self.cancel.store(true, AtomicOrdering::Relaxed);
+    }
+}
+
+impl Drop for ExportJob {
+    fn drop(&mut self) {
+        self.cancel.store(true, AtomicOrdering::Relaxed);
+    }
+}
+
+pub struct Export {
+    format: Format,
+    scope: Scope,
+    dialog: efd::FileDialog,
+    job: Option<ExportJob>,
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Self {
+            format: Format::Csv,
+            scope: Scope::Page,
+            dialog: efd::FileDialog::new(),
+            job: None,
+        }
+    }
+}
+
+impl EntityDb {
+    pub(super) fn show_export(&mut self, ui: &mut egui::Ui) {
+        let has_rows = self.row_ids().is_some();
+        let ex = &mut self.export;
+        if ex.scope == Scope::Rows && !has_rows {
+            ex.scope = Scope::Page;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("export:");
+            ui.selectable_value(&mut ex.scope, Scope::Page, "visible");
+            if has_rows {
+                ui.selectable_value(&mut ex.scope, Scope::Rows, "result");
+            }
+            ui.selectable_value(&mut ex.scope, Scope::File, "whole file");
+            ui.separator();
+            for f in Format::ALL {
+                ui.selectable_value(&mut ex.format, f, f.ext());
+            }
+            let busy = ex.job.as_ref().is_some_and(|j| !j.done());
+            if ui.add_enabled(!busy, egui::Button::new("save as…")).clicked()
+            {
+                ex.dialog = efd::FileDialog::new().default_file_name(&format!(
+                    "{}.{}.{}",
+                    self.name,
+                    self.revision,
+                    ex.format.ext()
+                ));
+                ex.dialog.save_file();
+            }
+
+            let Some(job) = &mut ex.job else { return };
+            job.poll();
+            if !job.done() {
+                ui.spinner();
+                if ui.button("cancel").clicked() {
+                    job.cancel();
+                }
+            }
+            if job.canceled {
+                ui.label(format!(
+                    "export to {:?} was canceled, nothing was written",
+                    job.path
+                ));
+                return;
+            }
+            ui.label(format!("{:?}: {}/{}", job.path, job.written, job.total));
+            if let Some(e) = &job.error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+        });
+
+        self.export.dialog.update(ui.ctx());
+        let Some(path) = self.export.dialog.take_picked() else { return };
+        let ids = match self.export.scope {
+            Scope::Page => Some(self.visible_ids()),
+            Scope::Rows => self.row_ids().map(|ids| ids.to_vec()),
+            Scope::File => None,
+        };
+        let cols = self
+            .fields
+            .iter()
+            .filter(|f| f.visible)
+            .map(|f| (f.key.clone(), f.path.clone(), f.range.clone()))
+            .collect();
+        let job = ExportJob::start(
+            &self.file,
+            path,
+            self.export.format,
+            cols,
+            self.item_size,
+            self.item_total,
+            ids,
+            ui.ctx().clone(),
+        );
+        match job {
+            Ok(job) => self.export.job = Some(job),
+            Err(e) => log::error!("could not start export: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn renamed_when_done() {
+        let db = EntityDb::init(fixtures::get().item.clone()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("items.csv");
+        let cols = db
+            .fields
+            .iter()
+            .filter(|f| f.key == "age")
+            .map(|f| (f.key.clone(), f.path.clone(), f.range.clone()))
+            .collect();
+        let mut job = ExportJob::start(
+            &db.file,
+            path.clone(),
+            Format::Csv,
+            cols,
+            db.item_size,
+            db.item_total,
+            None,
+            egui::Context::default(),
+        )
+        .unwrap();
+        while !job.done() {
+            job.poll();
+            std::thread::yield_now();
+        }
+        assert_eq!((job.error.as_ref(), job.canceled), (None, false));
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, "id,age\n0,0\n1,10\n2,20\n3,30\n4,40\n");
+        assert!(!dir.path().join("items.csv.part").exists());
+    }
+}
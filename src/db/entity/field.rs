@@ -1,19 +1,8 @@
-use shah::models::{Binary, Gene, Schema};
+use crate::db::nav;
+use crate::value::{clone_schema, Decoded};
+use shah::models::{Binary, Gene, Schema, SchemaModel};
 use std::ops::Range;
-
-fn show_gene(v: &[u8], ui: &mut egui::Ui) {
-    if !v.iter().any(|x| *x != 0) {
-        ui.label("---");
-        return;
-    }
-
-    let g = Gene::from_binary(v);
-
-    ui.label(format!(
-        "Gene({}, {}, {:?}, {})",
-        g.id, g.iter, g.pepper, g.server
-    ));
-}
+use std::sync::Arc;
 
 macro_rules! schema_numbers {
     ($kind:expr, $macro:ident $($arg:tt)*) => {
@@ -33,64 +22,172 @@ macro_rules! schema_numbers {
     };
 }
 
-type NumberStats = Option<fn(list: &[Vec<u8>], range: Range<usize>) -> String>;
-type NumberSort = Option<fn(item: &[u8]) -> u64>;
+/// primitive type of a value, used to decode raw field bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prim {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    Bool,
+    Gene,
+}
+
+/// the shape of a field as far as filtering and sorting are concerned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Prim(Prim),
+    Str,
+    Array(Prim),
+    Other,
+}
+
+impl Prim {
+    pub fn of(schema: &Schema) -> Option<Self> {
+        Some(match schema {
+            Schema::U8 => Self::U8,
+            Schema::U16 => Self::U16,
+            Schema::U32 => Self::U32,
+            Schema::U64 => Self::U64,
+            Schema::I8 => Self::I8,
+            Schema::I16 => Self::I16,
+            Schema::I32 => Self::I32,
+            Schema::I64 => Self::I64,
+            Schema::F32 => Self::F32,
+            Schema::F64 => Self::F64,
+            Schema::Bool => Self::Bool,
+            Schema::Gene => Self::Gene,
+            _ => return None,
+        })
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            Self::U8 | Self::I8 | Self::Bool => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+            Self::U64 | Self::I64 | Self::F64 => 8,
+            Self::Gene => Gene::S,
+        }
+    }
+
+    pub fn is_number(self) -> bool {
+        !matches!(self, Self::Bool | Self::Gene)
+    }
+}
+
+impl Kind {
+    pub fn sortable(self) -> bool {
+        matches!(self, Self::Prim(_) | Self::Str)
+    }
+
+    pub fn of(schema: &Schema) -> Self {
+        if let Some(p) = Prim::of(schema) {
+            return Self::Prim(p);
+        }
+        match schema {
+            Schema::Array { is_str: true, kind, .. }
+                if matches!(**kind, Schema::U8) =>
+            {
+                Self::Str
+            }
+            Schema::Array { kind, .. } => match Prim::of(kind) {
+                Some(p) => Self::Array(p),
+                None => Self::Other,
+            },
+            _ => Self::Other,
+        }
+    }
+}
+
+/// the schema of a field, following `path` through nested models and tuples
+pub fn schema_at<'a>(
+    model: &'a SchemaModel, path: &[usize],
+) -> Option<&'a Schema> {
+    let (first, rest) = path.split_first()?;
+    let mut schema = &model.fields.get(*first)?.1;
+    for i in rest {
+        schema = match schema {
+            Schema::Model(m) => &m.fields.get(*i)?.1,
+            Schema::Tuple(list) => list.get(*i)?,
+            _ => return None,
+        };
+    }
+    Some(schema)
+}
 
 #[derive(Debug, Clone)]
 pub struct Field {
-    pub idx: usize,
+    /// indexes through the schema model and nested models or tuples
+    pub path: Vec<usize>,
     pub range: Range<usize>,
-    pub show: fn(value: &[u8], ui: &mut egui::Ui),
+    pub schema: Arc<Schema>,
+    /// the field name as it appears in the schema
+    pub key: String,
+    pub kind: Kind,
     pub name: String,
     pub visible: bool,
     pub show_array: bool,
-    pub number_stats: NumberStats,
-    pub number_sort: NumberSort,
 }
 
 impl Field {
-pub fn get_number_sort(schema: &Schema) -> NumberSort {
-        macro_rules! schema_num_show {
-            ($ty:ty) => {{
-                fn num_sort(v: &[u8]) -> u64 {
-                    let vp = <$ty>::from_le_bytes(v.try_into().unwrap());
-                    vp as u64
-                }
-                return Some(num_sort);
-            }};
-        }
-        schema_numbers!(schema, schema_num_show);
-
-        None
+    /// one field per leaf of the model. nested models and tuples are
+    /// expanded into dotted fields like `address.city` and `pos.0`
+    pub fn from_model(schema: &SchemaModel) -> Vec<Field> {
+        let mut fields = Vec::<Field>::with_capacity(schema.fields.len());
+        let items = schema.fields.iter().map(|(n, s)| (n.clone(), s)).collect();
+        Field::flatten(items, "", &[], 0, &mut fields);
+        fields
     }
 
-    pub fn get_number_stats(schema: &Schema) -> NumberStats {
-        macro_rules! schema_num_show {
-            ($ty:ty) => {{
-                fn num_stats(list: &[Vec<u8>], range: Range<usize>) -> String {
-                    let mut min = <$ty>::default();
-                    let mut max = <$ty>::default();
-                    let mut total = usize::default();
-                    for item in list {
-                        let v = &item[range.clone()];
-                        let vp = <$ty>::from_le_bytes(v.try_into().unwrap());
-                        min = min.min(vp);
-                        max = max.max(vp);
-                        total += vp as usize;
-                    }
-                    let avg = if !list.is_empty() {
-                        total / list.len()
-                    } else {
-                        Default::default()
-                    };
-                    format!("min/max/avg/total: {min}/{max}/{avg}/{total}")
+    fn flatten(
+        items: Vec<(String, &Schema)>, prefix: &str, path: &[usize],
+        mut i: usize, fields: &mut Vec<Field>,
+    ) {
+        for (fdx, (fi, fs)) in items.into_iter().enumerate() {
+            let s = fs.size();
+            let key = format!("{prefix}{fi}");
+            let mut path = path.to_vec();
+            path.push(fdx);
+            match fs {
+                Schema::Model(m) => {
+                    let items =
+                        m.fields.iter().map(|(n, s)| (n.clone(), s)).collect();
+                    Field::flatten(items, &format!("{key}."), &path, i, fields);
                 }
-                return Some(num_stats);
-            }};
+                Schema::Tuple(list) => {
+                    let items = list
+                        .iter()
+                        .enumerate()
+                        .map(|(n, s)| (n.to_string(), s))
+                        .collect();
+                    Field::flatten(items, &format!("{key}."), &path, i, fields);
+                }
+                _ => {
+                    let show_array = !matches!(
+                        fs,
+                        Schema::Array { kind, .. } if matches!(**kind, Schema::Gene)
+                    );
+                    fields.push(Field {
+                        path,
+                        name: format!("{key}: {}", Field::get_ty(fs)),
+                        key,
+                        kind: Kind::of(fs),
+                        range: i..i + s,
+                        schema: Arc::new(clone_schema(fs)),
+                        show_array,
+                        visible: true,
+                    });
+                }
+            }
+            i += s;
         }
-        schema_numbers!(schema, schema_num_show);
-
-        None
     }
 
     pub fn get_ty(schema: &Schema) -> String {
@@ -122,89 +219,50 @@ pub fn get_number_sort(schema: &Schema) -> NumberSort {
         }
     }
 
-    pub fn get_show(schema: &Schema) -> (fn(&[u8], &mut egui::Ui), bool) {
-        fn do_nothing(_: &[u8], _: &mut egui::Ui) {}
-
-        macro_rules! schema_num_show {
-            ($ty:ty) => {{
-                fn show_prim_num(v: &[u8], ui: &mut egui::Ui) {
-                    let vp = <$ty>::from_le_bytes(v.try_into().unwrap());
-                    ui.label(vp.to_string());
-                }
-                return (show_prim_num, true);
-            }};
-        }
-        schema_numbers!(schema, schema_num_show);
+    /// decodes the bytes of this field
+    pub fn decode(&self, v: &[u8]) -> Decoded {
+        Decoded::decode(&self.schema, v)
+    }
 
-        match schema {
-            Schema::Bool => {
-                fn show_prim_bool(v: &[u8], ui: &mut egui::Ui) {
-                    let vp = u8::from_le_bytes(v.try_into().unwrap()) != 0;
-                    ui.label(vp.to_string());
-                }
-                return (show_prim_bool, true);
-            }
-            Schema::Gene => {
-                return (show_gene, true);
-            }
-            Schema::Array { is_str, kind, .. } => {
-                if matches!(**kind, Schema::Gene) {
-                    fn show_genes(v: &[u8], ui: &mut egui::Ui) {
-                        let list = v.chunks(Gene::S).enumerate();
-                        let list = list.collect::<Vec<_>>();
-                        let scroll = egui::ScrollArea::vertical();
-                        let scroll = scroll.auto_shrink([false, false]);
-                        scroll.show_rows(ui, 20.0, list.len(), |ui, s| {
-                            for (i, c) in &list[s] {
-                                ui.horizontal(|ui| {
-                                    ui.label(format!("{i}:"));
-                                    show_gene(c, ui);
-                                });
-                            }
-                        });
-                    }
-                    return (show_genes, false);
-                }
+    pub fn is_number(&self) -> bool {
+        matches!(self.kind, Kind::Prim(p) if p.is_number())
+    }
 
-                if matches!(**kind, Schema::U8) && *is_str {
-                    fn show_str(v: &[u8], ui: &mut egui::Ui) {
-                        let sv = v.splitn(2, |x| *x == 0).next().unwrap();
-                        let s = match core::str::from_utf8(sv) {
-                            Ok(v) => v,
-                            Err(e) => {
-                                core::str::from_utf8(&v[..e.valid_up_to()])
-                                    .unwrap()
-                            }
-                        };
-                        if s.is_empty() {
-                            ui.label(
-                                egui::RichText::new("<empty>")
-                                    .color(egui::Color32::PURPLE),
-                            );
-                        } else {
-                            ui.label(s);
-                        }
-                    }
-                    return (show_str, true);
-                }
+    /// a table cell of the field bytes `v`
+    pub fn show(&self, v: &[u8], ui: &mut egui::Ui) {
+        show_value(&self.decode(v), ui);
+    }
+}
 
-                macro_rules! schema_num_align {
-                    ($ty:ty) => {{
-                        fn show_prim_arr(v: &[u8], ui: &mut egui::Ui) {
-                            let (head, data, tail) =
-                                unsafe { v.align_to::<$ty>() };
-                            assert!(head.is_empty());
-                            assert!(tail.is_empty());
-                            ui.label(format!("{data:?}"));
-                        }
-                        return (show_prim_arr, true);
-                    }};
+fn show_value(value: &Decoded, ui: &mut egui::Ui) {
+    match value {
+        Decoded::Gene(_) if value.is_zero() => {
+            ui.label("---");
+        }
+        Decoded::Gene(g) => {
+            nav::gene_link(g, ui);
+        }
+        Decoded::Str(s) if s.is_empty() => {
+            ui.label(
+                egui::RichText::new("<empty>").color(egui::Color32::PURPLE),
+            );
+        }
+        Decoded::Array(list)
+            if matches!(list.first(), Some(Decoded::Gene(_))) =>
+        {
+            let scroll = egui::ScrollArea::vertical();
+            let scroll = scroll.auto_shrink([false, false]);
+            scroll.show_rows(ui, 20.0, list.len(), |ui, s| {
+                for (i, v) in list[s.clone()].iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}:", s.start + i));
+                        show_value(v, ui);
+                    });
                 }
-                schema_numbers!(&(**kind), schema_num_align);
-            }
-            _ => {}
+            });
+        }
+        v => {
+            ui.label(v.text());
         }
-
-        (do_nothing, true)
     }
 }
@@ -0,0 +1,732 @@
+use super::field::{Field, Kind, Prim};
+use super::EntityDb;
+use crate::value::Decoded;
+use shah::db::entity::ENTITY_META;
+use shah::models::{Gene, Schema};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::ops::Range;
+use std::os::unix::fs::FileExt;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+/// how many items are read from disk at once while scanning
+pub(super) const SCAN_BATCH: u64 = 4096;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Num(String),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+    LBrack,
+    RBrack,
+    Comma,
+    Ellipsis,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// contains, case insensitive for strings
+    Has,
+}
+
+#[derive(Debug, Clone, Default)]
+struct GenePat {
+    id: Option<u64>,
+    iter: Option<u8>,
+    pepper: Option<[u8; 3]>,
+    server: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+enum Lit {
+    Int(i128),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    Gene(GenePat),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp { range: Range<usize>, schema: Arc<Schema>, op: Op, lit: Lit },
+}
+
+/// a compiled filter expression
+///
+/// ```text
+/// expr := expr || expr | expr && expr | !expr | (expr) | field op literal
+/// op   := == != < <= > >= ~
+/// lit  := 12 | -1.5 | "text" | true | false | Gene(id, iter, [p, e, p], server)
+/// ```
+///
+/// `~` is a case insensitive substring match on strings and an
+/// "any element equals" match on arrays. gene components can be
+/// replaced with `_` and trailing ones can be left out with `...`
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr,
+}
+
+fn lex(src: &str) -> Result<Vec<Token>, String> {
+    let chars = src.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0usize;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+            ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+            ('<', Some('=')) => (Token::Op(Op::Le), 2),
+            ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('~', _) => (Token::Op(Op::Has), 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('[', _) => (Token::LBrack, 1),
+            (']', _) => (Token::RBrack, 1),
+            (',', _) => (Token::Comma, 1),
+            ('.', Some('.')) if chars.get(i + 2) == Some(&'.') => {
+                (Token::Ellipsis, 3)
+            }
+            ('"', _) => {
+                let mut s = String::new();
+                let mut j = i + 1;
+                loop {
+                    match chars.get(j) {
+                        None => return Err("unterminated string".to_string()),
+                        Some('"') => break,
+                        Some('\\') if j + 1 < chars.len() => {
+                            s.push(chars[j + 1]);
+                            j += 2;
+                        }
+                        Some(c) => {
+                            s.push(*c);
+                            j += 1;
+                        }
+                    }
+                }
+                (Token::Str(s), j + 1 - i)
+            }
+            (c, _) if c.is_ascii_digit() || c == '-' => {
+                let mut j = i + 1;
+                while j < chars.len()
+                    && (chars[j].is_ascii_digit()
+                        || matches!(chars[j], '.' | 'e' | 'E' | '_'))
+                {
+                    j += 1;
+                }
+                (Token::Num(chars[i..j].iter().collect()), j - i)
+            }
+            (c, _) if c.is_alphabetic() || c == '_' => {
+                let mut j = i + 1;
+                while j < chars.len()
+                    && (chars[j].is_alphanumeric()
+                        || matches!(chars[j], '_' | '.'))
+                {
+                    j += 1;
+                }
+                (Token::Ident(chars[i..j].iter().collect()), j - i)
+            }
+            (c, _) => return Err(format!("unexpected character: {c:?}")),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    fields: &'a [Field],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == token => Ok(()),
+            Some(t) => Err(format!("expected {token:?} found {t:?}")),
+            None => Err(format!("expected {token:?} found the end")),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            lhs = Expr::And(Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.or()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            _ => self.cmp(),
+        }
+    }
+
+    fn cmp(&mut self) -> Result<Expr, String> {
+        let name = match self.next() {
+            Some(Token::Ident(name)) => name,
+            Some(t) => return Err(format!("expected a field found {t:?}")),
+            None => return Err("expected a field found the end".to_string()),
+        };
+        let Some(field) = self.fields.iter().find(|f| f.key == name) else {
+            return Err(format!("unknown field: {name}"));
+        };
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            t => return Err(format!("expected an operator found {t:?}")),
+        };
+        let lit = self.lit()?;
+        check(&name, field.kind, op, &lit)?;
+
+        Ok(Expr::Cmp {
+            range: field.range.clone(),
+            schema: field.schema.clone(),
+            op,
+            lit,
+        })
+    }
+
+    fn lit(&mut self) -> Result<Lit, String> {
+        match self.next() {
+            Some(Token::Num(n)) => parse_num(&n),
+            Some(Token::Str(s)) => Ok(Lit::Str(s)),
+            Some(Token::Ident(i)) if i == "true" => Ok(Lit::Bool(true)),
+            Some(Token::Ident(i)) if i == "false" => Ok(Lit::Bool(false)),
+            Some(Token::Ident(i)) if i == "Gene" => self.gene(),
+            t => Err(format!("expected a literal found {t:?}")),
+        }
+    }
+
+    fn gene(&mut self) -> Result<Lit, String> {
+        self.expect(Token::LParen)?;
+        let mut pat = GenePat::default();
+        for idx in 0..4 {
+            if idx != 0 {
+                match self.peek() {
+                    Some(Token::Comma) => self.pos += 1,
+                    _ => break,
+                }
+            }
+            match self.next() {
+                Some(Token::Ellipsis) => break,
+                Some(Token::Ident(i)) if i == "_" => continue,
+                Some(Token::LBrack) if idx == 2 => {
+                    let mut pepper = [0u8; 3];
+                    for (i, p) in pepper.iter_mut().enumerate() {
+                        if i != 0 {
+                            self.expect(Token::Comma)?;
+                        }
+                        *p = self.gene_part("pepper")?;
+                    }
+                    self.expect(Token::RBrack)?;
+                    pat.pepper = Some(pepper);
+                }
+                Some(Token::Num(n)) => {
+                    let n = n.parse::<u64>().map_err(|e| e.to_string())?;
+                    let err = || format!("gene part {idx} is out of range");
+                    match idx {
+                        0 => pat.id = Some(n),
+                        1 => pat.iter = Some(n.try_into().map_err(|_| err())?),
+                        3 => {
+                            pat.server = Some(n.try_into().map_err(|_| err())?)
+                        }
+                        _ => return Err("pepper must be [p, e, p]".to_string()),
+                    }
+                }
+                t => return Err(format!("invalid gene part: {t:?}")),
+            }
+        }
+        self.expect(Token::RParen)?;
+        Ok(Lit::Gene(pat))
+    }
+
+    fn gene_part(&mut self, name: &str) -> Result<u8, String> {
+        match self.next() {
+            Some(Token::Num(n)) => n.parse::<u8>().map_err(|e| e.to_string()),
+            t => Err(format!("expected {name} number found {t:?}")),
+        }
+    }
+}
+
+fn parse_num(n: &str) -> Result<Lit, String> {
+    let n = n.replace('_', "");
+    if let Ok(i) = n.parse::<i128>() {
+        return Ok(Lit::Int(i));
+    }
+    match n.parse::<f64>() {
+        Ok(f) => Ok(Lit::Float(f)),
+        Err(_) => Err(format!("invalid number: {n}")),
+    }
+}
+
+fn check_prim(name: &str, prim: Prim, op: Op, lit: &Lit) -> Result<(), String> {
+    let eq = matches!(op, Op::Eq | Op::Ne | Op::Has);
+    let ok = match (prim, lit) {
+        (Prim::Bool, Lit::Bool(_)) => eq,
+        (Prim::Gene, Lit::Gene(_)) => eq,
+        (Prim::Bool | Prim::Gene, _) => false,
+        (_, Lit::Int(_) | Lit::Float(_)) => true,
+        _ => false,
+    };
+    if ok {
+        return Ok(());
+    }
+    Err(format!("{name} of {prim:?} can not be compared with {op:?} {lit:?}"))
+}
+
+fn check(name: &str, kind: Kind, op: Op, lit: &Lit) -> Result<(), String> {
+    match kind {
+        Kind::Prim(_) if op == Op::Has => {
+            Err(format!("~ is only valid for strings and arrays: {name}"))
+        }
+        Kind::Prim(p) => check_prim(name, p, op, lit),
+        Kind::Str if matches!(lit, Lit::Str(_)) => Ok(()),
+        Kind::Str => Err(format!("{name} is a string: use \"...\"")),
+        Kind::Array(p) if op == Op::Has => check_prim(name, p, op, lit),
+        Kind::Array(_) => Err(format!("{name} is an array: only ~ is valid")),
+        Kind::Other => Err(format!("{name} can not be filtered")),
+    }
+}
+
+fn gene_matches(g: &Gene, pat: &GenePat) -> bool {
+    pat.id.is_none_or(|v| g.id.0 == v)
+        && pat.iter.is_none_or(|v| g.iter == v)
+        && pat.pepper.is_none_or(|v| g.pepper == v)
+        && pat.server.is_none_or(|v| g.server == v)
+}
+
+fn value_cmp(value: &Decoded, lit: &Lit) -> Option<Ordering> {
+    match (value, lit) {
+        (v, Lit::Int(b)) => match v.as_i128() {
+            Some(a) => Some(a.cmp(b)),
+            None => v.as_f64()?.partial_cmp(&(*b as f64)),
+        },
+        (v, Lit::Float(b)) => v.as_f64()?.partial_cmp(b),
+        (Decoded::Bool(a), Lit::Bool(b)) => Some(a.cmp(b)),
+        (Decoded::Str(a), Lit::Str(b)) => Some(a.as_str().cmp(b)),
+        (Decoded::Gene(a), Lit::Gene(b)) => Some(if gene_matches(a, b) {
+            Ordering::Equal
+        } else {
+            Ordering::Less
+        }),
+        _ => None,
+    }
+}
+
+fn value_test(value: &Decoded, op: Op, lit: &Lit) -> bool {
+    if op == Op::Has {
+        return match (value, lit) {
+            (Decoded::Str(a), Lit::Str(b)) => {
+                a.to_lowercase().contains(&b.to_lowercase())
+            }
+            (Decoded::Array(list), lit) => {
+                list.iter().any(|v| value_test(v, Op::Eq, lit))
+            }
+            _ => false,
+        };
+    }
+
+    let Some(ord) = value_cmp(value, lit) else { return false };
+    match op {
+        Op::Eq => ord.is_eq(),
+        Op::Ne => ord.is_ne(),
+        Op::Lt => ord.is_lt(),
+        Op::Le => ord.is_le(),
+        Op::Gt => ord.is_gt(),
+        Op::Ge => ord.is_ge(),
+        Op::Has => unreachable!(),
+    }
+}
+
+impl Expr {
+    fn eval(&self, item: &[u8]) -> bool {
+        match self {
+            Self::And(a, b) => a.eval(item) && b.eval(item),
+            Self::Or(a, b) => a.eval(item) || b.eval(item),
+            Self::Not(a) => !a.eval(item),
+            Self::Cmp { range, schema, op, lit } => {
+                let value = Decoded::decode(schema, &item[range.clone()]);
+                value_test(&value, *op, lit)
+            }
+        }
+    }
+}
+
+impl Filter {
+    pub fn parse(src: &str, fields: &[Field]) -> Result<Self, String> {
+        let tokens = lex(src)?;
+        if tokens.is_empty() {
+            return Err("empty filter".to_string());
+        }
+        let mut parser = Parser { tokens, pos: 0, fields };
+        let expr = parser.or()?;
+        if let Some(t) = parser.peek() {
+            return Err(format!("unexpected {t:?}"));
+        }
+        Ok(Self { expr })
+    }
+
+    pub fn matches(&self, item: &[u8]) -> bool {
+        self.expr.eval(item)
+    }
+}
+
+enum ScanMsg {
+    Progress { scanned: u64, ids: Vec<u64> },
+    Error(String),
+}
+
+/// a background scan of the whole entity file with a filter
+pub struct FilterScan {
+    pub matched: Vec<u64>,
+    pub scanned: u64,
+    pub total: u64,
+    pub error: Option<String>,
+    filter: Filter,
+    file: File,
+    item_size: u64,
+    rx: Receiver<ScanMsg>,
+    cancel: Arc<AtomicBool>,
+    done: bool,
+}
+
+/// scans the items in `ids` on a new thread
+fn scan(
+    file: &File, filter: Filter, item_size: u64, ids: Range<u64>,
+    ctx: egui::Context,
+) -> std::io::Result<(Receiver<ScanMsg>, Arc<AtomicBool>)> {
+    let file = file.try_clone()?;
+    let (tx, rx) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let stop = cancel.clone();
+
+    std::thread::spawn(move || {
+        let mut buf = vec![0u8; (SCAN_BATCH * item_size) as usize];
+        let Range { start: mut id, end: total } = ids;
+        while id < total && !stop.load(AtomicOrdering::Relaxed) {
+            let n = SCAN_BATCH.min(total - id);
+            let buf = &mut buf[..(n * item_size) as usize];
+            let pos = ENTITY_META + id * item_size;
+            if let Err(e) = file.read_exact_at(buf, pos) {
+                let _ = tx.send(ScanMsg::Error(e.to_string()));
+                break;
+            }
+            let chunks = buf.chunks(item_size as usize).enumerate();
+            let ids = chunks
+                .filter(|(_, item)| filter.matches(item))
+                .map(|(i, _)| id + i as u64)
+                .collect::<Vec<_>>();
+
+            id += n;
+            if tx.send(ScanMsg::Progress { scanned: id, ids }).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        }
+    });
+
+    Ok((rx, cancel))
+}
+
+impl FilterScan {
+    pub fn start(
+        file: &File, filter: Filter, item_size: u64, total: u64,
+        ctx: egui::Context,
+    ) -> std::io::Result<Self> {
+        let (rx, cancel) =
+            scan(file, filter.clone(), item_size, 0..total, ctx)?;
+        Ok(Self {
+            matched: Vec::new(),
+            scanned: 0,
+            total,
+            error: None,
+            filter,
+            file: file.try_clone()?,
+            item_size,
+            rx,
+            cancel,
+            done: total == 0,
+        })
+    }
+
+    /// scans the items appended since the scan was started. the matches
+    /// received so far are kept and the rest is scanned again
+    pub fn extend(
+        &mut self, total: u64, ctx: egui::Context,
+    ) -> std::io::Result<()> {
+        self.poll();
+        if total <= self.total || self.error.is_some() {
+            return Ok(());
+        }
+        let ids = self.scanned..total;
+        let (rx, cancel) =
+            scan(&self.file, self.filter.clone(), self.item_size, ids, ctx)?;
+        self.cancel.store(true, AtomicOrdering::Relaxed);
+        self.rx = rx;
+        self.cancel = cancel;
+        self.total = total;
+        self.done = false;
+        Ok(())
+    }
+
+    /// collects the results sent so far. returns true if anything changed
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        loop {
+            match self.rx.try_recv() {
+                Ok(ScanMsg::Progress { scanned, ids }) => {
+                    self.scanned = scanned;
+                    self.matched.extend(ids);
+                    changed = true;
+                }
+                Ok(ScanMsg::Error(e)) => {
+                    self.error = Some(e);
+                    self.done = true;
+                    return true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    changed |= !self.done;
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        changed
+    }
+
+    pub fn done(&self) -> bool {
+        self.done
+    }
+}
+
+impl Drop for FilterScan {
+    fn drop(&mut self) {
+        self.cancel.store(true, AtomicOrdering::Relaxed);
+    }
+}
+
+impl EntityDb {
+    pub(super) fn show_filter(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("filter:");
+            let res = ui.add(
+                egui::TextEdit::singleline(&mut self.filter_query)
+                    .hint_text("age > 30 && name ~ \"ali\"")
+                    .desired_width(400.0),
+            );
+            let enter = res.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("apply").clicked() || enter {
+                self.apply_filter(ui.ctx().clone());
+            }
+            if self.filter.is_some() && ui.button("clear").clicked() {
+                self.filter_query.clear();
+                self.apply_filter(ui.ctx().clone());
+            }
+
+            let Some(scan) = &self.filter else { return };
+            if !scan.done() {
+                ui.spinner();
+            }
+            ui.label(format!(
+                "matched: {} | scanned: {}/{}",
+                scan.matched.len(),
+                scan.scanned,
+                scan.total
+            ));
+            if let Some(e) = &scan.error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+        });
+        if let Some(e) = &self.filter_error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+    }
+
+    pub(super) fn apply_filter(&mut self, ctx: egui::Context) {
+        self.filter = None;
+        self.filter_error = None;
+        self.active_item = None;
+        self.scroll_to = Some(0);
+
+        if !self.filter_query.trim().is_empty() {
+            let scan = Filter::parse(&self.filter_query, &self.fields)
+                .and_then(|filter| {
+                    FilterScan::start(
+                        &self.file,
+                        filter,
+                        self.item_size,
+                        self.item_total,
+                        ctx.clone(),
+                    )
+                    .map_err(|e| e.to_string())
+                });
+            match scan {
+                Ok(scan) => self.filter = Some(scan),
+                Err(e) => self.filter_error = Some(e),
+            }
+        }
+
+        self.restart_sort(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    /// ids of the fixture items that match `query`
+    fn ids(query: &str) -> Result<Vec<u64>, String> {
+        let db = EntityDb::init(fixtures::get().item.clone()).unwrap();
+        let filter = Filter::parse(query, &db.fields)?;
+        let mut buf = vec![0u8; db.item_size as usize];
+        let ids = (0..db.item_total).filter(|id| {
+            let pos = ENTITY_META + id * db.item_size;
+            db.file.read_exact_at(&mut buf, pos).unwrap();
+            filter.matches(&buf)
+        });
+        Ok(ids.collect())
+    }
+
+    fn err(query: &str) -> String {
+        ids(query).unwrap_err()
+    }
+
+    #[test]
+    fn lexer() {
+        let tokens = lex(r#"pos.x>=-1.5&&!(name~"a\"b")"#).unwrap();
+        assert_eq!(
+            tokens,
+            [
+                Token::Ident("pos.x".to_string()),
+                Token::Op(Op::Ge),
+                Token::Num("-1.5".to_string()),
+                Token::And,
+                Token::Not,
+                Token::LParen,
+                Token::Ident("name".to_string()),
+                Token::Op(Op::Has),
+                Token::Str("a\"b".to_string()),
+                Token::RParen,
+            ]
+        );
+        let tokens = lex("Gene(1, _, ...)").unwrap();
+        assert_eq!(tokens[4], Token::Ident("_".to_string()));
+        assert_eq!(tokens[6], Token::Ellipsis);
+        assert_eq!(lex("age # 1").unwrap_err(), "unexpected character: '#'");
+    }
+
+    #[test]
+    fn precedence() {
+        // && binds tighter than ||
+        let q = "age == 10 || age == 20 && active == true";
+        assert_eq!(ids(q).unwrap(), [1]);
+        let q = "(age == 10 || age == 20) && active == false";
+        assert_eq!(ids(q).unwrap(), [2]);
+        // ! applies to the comparison after it
+        assert_eq!(ids("!active == true && age > 0").unwrap(), [2, 4]);
+        assert_eq!(ids("score > 0.5 && pos.y >= -3").unwrap(), [3]);
+    }
+
+    #[test]
+    fn genes() {
+        assert_eq!(ids("gene == Gene(2, ...)").unwrap(), [2]);
+        let q = "owner == Gene(1, _, [1, 2, 3], 1)";
+        assert_eq!(ids(q).unwrap(), [2, 3]);
+        assert_eq!(ids("owner != Gene(1, ...)").unwrap(), [0, 1, 4]);
+        assert_eq!(ids("friends ~ Gene(3, 0)").unwrap(), [4]);
+    }
+
+    #[test]
+    fn has() {
+        assert_eq!(ids(r#"name ~ "EE""#).unwrap(), [3]);
+        assert_eq!(ids(r#"name ~ "a""#).unwrap(), [1, 2, 4]);
+        assert_eq!(ids(r#"name == "bread""#).unwrap(), [2]);
+    }
+
+    #[test]
+    fn type_errors() {
+        assert_eq!(err("name > 3"), r#"name is a string: use "...""#);
+        assert_eq!(
+            err("age ~ 3"),
+            "~ is only valid for strings and arrays: age"
+        );
+        assert_eq!(
+            err("friends == Gene(1, ...)"),
+            "friends is an array: only ~ is valid"
+        );
+        assert!(ids("active < true").is_err());
+        assert!(ids("gene == 1").is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(err(""), "empty filter");
+        assert_eq!(err("nope == 1"), "unknown field: nope");
+        assert_eq!(err("age >"), "expected a literal found None");
+        assert_eq!(err("(age == 1"), "expected RParen found the end");
+        assert_eq!(err(r#"name == "x"#), "unterminated string");
+        assert_eq!(err("age == 1 age"), r#"unexpected Ident("age")"#);
+        assert_eq!(err("gene == Gene(1, 2, 3)"), "pepper must be [p, e, p]");
+    }
+}
@@ -1,12 +1,53 @@
-use super::EntityDb;
+use super::{sort, ActiveItem, EntityDb};
+use crate::db::show_schema;
 use egui_extras as ee;
 use shah::db::entity::ENTITY_META;
+use std::fs::File;
+use std::ops::Range;
 use std::os::unix::fs::FileExt;
 
+pub(super) type Rows = Result<(Vec<u64>, Vec<Vec<u8>>), String>;
+
+/// reads the items with the given ids. runs of consecutive ids are read
+/// with a single call
+pub(super) fn read_rows(file: &File, item_size: u64, ids: Vec<u64>) -> Rows {
+    let size = item_size as usize;
+    let mut data = Vec::with_capacity(ids.len());
+    let mut i = 0;
+    while i < ids.len() {
+        let mut n = 1;
+        while i + n < ids.len() && ids[i + n] == ids[i] + n as u64 {
+            n += 1;
+        }
+        let mut buf = vec![0u8; n * size];
+        let pos = ENTITY_META + ids[i] * item_size;
+        file.read_exact_at(&mut buf, pos)
+            .map_err(|e| format!("could not read item {}: {e}", ids[i]))?;
+        data.extend(buf.chunks(size).map(|c| c.to_vec()));
+        i += n;
+    }
+    Ok((ids, data))
+}
+
+/// height of one table row
+const ROW_HEIGHT: f32 = 18.0;
+
 impl EntityDb {
     pub(super) fn show_items(&mut self, ui: &mut egui::Ui) {
-        let fields = self.fields.iter().filter(|f| f.visible);
-        ee::TableBuilder::new(ui)
+        let fields = self.fields.iter().enumerate().filter(|(_, f)| f.visible);
+        let shift = ui.input(|i| i.modifiers.shift);
+        let mut sort_click = None;
+        let mut row_click = None;
+        let mut visible: Option<Range<u64>> = None;
+        let total = self.row_total();
+        let active = self.active_item.as_ref().map(|a| a.id);
+        let height = if active.is_some() {
+            ui.available_height() * 0.6
+        } else {
+            ui.available_height()
+        };
+
+        let mut table = ee::TableBuilder::new(ui)
             .column(ee::Column::auto().resizable(true).at_least(50.0))
             .columns(
                 ee::Column::remainder()
@@ -17,86 +58,204 @@ impl EntityDb {
             )
             .striped(true)
             .resizable(true)
-            .sense(egui::Sense::click())
+            .auto_shrink([false, true])
+            .max_scroll_height(height)
+            .sense(egui::Sense::click());
+        if let Some(row) = self.scroll_to.take() {
+            table =
+                table.scroll_to_row(row as usize, Some(egui::Align::Center));
+        }
+        table
             .header(25.0, |mut header| {
                 header.col(|ui| {
                     ui.heading("id");
                 });
-                for f in fields.clone() {
+                for (fi, f) in fields.clone() {
                     header.col(|ui| {
-                        if f.number_sort.is_some() {
-                            if ui.button(&f.name).clicked() {
-                                self.sort_by = Some(f.clone());
-                            }
-                        } else {
+                        if !f.kind.sortable() {
                             ui.heading(&f.name);
+                            return;
+                        }
+                        let keys = &self.sort_keys;
+                        let label = match keys
+                            .iter()
+                            .position(|k| k.field == fi)
+                        {
+                            Some(i) => {
+                                let arrow =
+                                    if keys[i].desc { "▼" } else { "▲" };
+                                if keys.len() > 1 {
+                                    format!("{} {arrow}{}", f.name, i + 1)
+                                } else {
+                                    format!("{} {arrow}", f.name)
+                                }
+                            }
+                            None => f.name.clone(),
+                        };
+                        let res = ui.button(label).on_hover_text(
+                            "click to sort, shift+click to add a sort key",
+                        );
+                        if res.clicked() {
+                            sort_click = Some(fi);
                         }
                     });
                 }
             })
             .body(|body| {
-                let skip = self.item_skip.main();
-                body.rows(18.0, self.item_data.len(), |mut row| {
-                    let idx = row.index();
-                    let item = &self.item_data[idx];
-                    let id = skip + idx as u64;
+                body.rows(ROW_HEIGHT, total as usize, |mut row| {
+                    let r = row.index() as u64;
+                    let v = visible.get_or_insert(r..r + 1);
+                    v.start = v.start.min(r);
+                    v.end = v.end.max(r + 1);
+                    let Some((id, item)) = self.pages.row(r) else {
+                        row.col(|ui| {
+                            ui.weak("…");
+                        });
+                        return;
+                    };
+                    row.set_selected(active == Some(id));
                     row.col(|ui| {
                         ui.label(id.to_string());
                     });
-                    for f in fields.clone() {
+                    for (_, f) in fields.clone() {
                         row.col(|ui| {
                             if !f.show_array {
                                 return;
                             };
-                            (f.show)(&item[f.range.clone()], ui);
+                            f.show(&item[f.range.clone()], ui);
                         });
                     }
                     if row.response().clicked() {
-                        if let Some(ax) = self.active_item.as_mut() {
-                            if *ax == idx {
-                                self.active_item = None;
-                            } else {
-                                *ax = idx;
-                            }
-                        } else {
-                            self.active_item = Some(idx);
-                        }
+                        row_click = Some(id);
                     }
                 });
             });
+
+        self.visible = visible.unwrap_or(0..0);
+        let ids = sort::row_ids(&self.sort, &self.filter);
+        self.pages.fetch(self.visible.clone(), ids, total, ui.ctx());
+
+        if let Some(id) = row_click {
+            if active == Some(id) {
+                self.active_item = None;
+            } else {
+                self.select_item(id);
+            }
+        }
+        if let Some(field) = sort_click {
+            self.toggle_sort(field, shift, ui.ctx().clone());
+        }
     }
 
+    /// the rows now map to other items, they are read again
     pub(super) fn update_items(&mut self) {
-        let skip = self.item_skip.main();
-        let show = self.item_show.main();
-        let max = (skip + show).min(self.item_total);
-        self.item_data.clear();
-        for id in skip..max {
-            let mut buf = vec![0u8; self.item_size as usize];
-            let pos = ENTITY_META + id * self.item_size;
-            self.file.read_exact_at(buf.as_mut_slice(), pos).expect("read");
-            self.item_data.push(buf)
+        self.pages.clear();
+    }
+
+    /// item id of a table row
+    pub(super) fn row_id(&self, row: u64) -> u64 {
+        match self.row_ids() {
+            Some(ids) => ids[row as usize],
+            None => row,
         }
-        if let Some(sb) = &self.sort_by {
-            if let Some(ns) = sb.number_sort {
-                self.item_data.sort_by_key(|item| ns(&item[sb.range.clone()]));
+    }
+
+    /// ids of the rows that were visible in the last frame
+    pub(super) fn visible_ids(&self) -> Vec<u64> {
+        let total = self.row_total();
+        let rows = self.visible.start.min(total)..self.visible.end.min(total);
+        rows.map(|r| self.row_id(r)).collect()
+    }
+
+    /// bytes of the visible rows that are already read
+    pub(super) fn visible_data(&self) -> Vec<Vec<u8>> {
+        self.visible
+            .clone()
+            .filter_map(|r| self.pages.row(r).map(|(_, d)| d.to_vec()))
+            .collect()
+    }
+
+    pub(super) fn select_item(&mut self, id: u64) {
+        let data = self.pages.find(id).map(|d| d.to_vec());
+        self.active_item = Some(ActiveItem { id, data });
+    }
+
+    /// scrolls to the row of the item `id` and selects it
+    pub fn goto(&mut self, id: u64) {
+        self.goto_id = id.to_string();
+        self.goto_error = None;
+        let row = match self.row_ids() {
+            Some(ids) => ids.iter().position(|x| *x == id),
+            None => (id < self.item_total).then_some(id as usize),
+        };
+        match row {
+            Some(row) => {
+                self.scroll_to = Some(row as u64);
+                self.select_item(id);
+            }
+            None => self.goto_error = Some(format!("{id} is not in the rows")),
+        }
+    }
+
+    pub(super) fn show_goto(&mut self, ui: &mut egui::Ui) {
+        ui.label("go to id:");
+        let res = ui.add(
+            egui::TextEdit::singleline(&mut self.goto_id).desired_width(120.0),
+        );
+        let enter =
+            res.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if ui.button("go").clicked() || enter {
+            match self.goto_id.trim().parse::<u64>() {
+                Ok(id) => self.goto(id),
+                Err(e) => self.goto_error = Some(e.to_string()),
             }
         }
+        if let Some(e) = &self.goto_error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+
+        let total = self.row_total();
+        if self.visible.is_empty() || total == 0 {
+            return;
+        }
+        let first = self.row_id(self.visible.start.min(total - 1));
+        let last = self.row_id(self.visible.end.min(total) - 1);
+        ui.separator();
+        ui.label(format!(
+            "rows {}..{} of {total} | ids {first}..={last}",
+            self.visible.start, self.visible.end
+        ));
     }
 
     pub(super) fn show_active_item(&mut self, ui: &mut egui::Ui) {
-        let Some(idx) = self.active_item else { return };
-        if idx >= self.item_data.len() {
+        let Some(active) = &mut self.active_item else { return };
+        if let Some(d) = self.pages.find(active.id) {
+            if active.data.as_deref() != Some(d) {
+                active.data = Some(d.to_vec());
+            }
+        }
+        if active.data.is_none() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("loading item {}", active.id));
+            });
             return;
         }
 
-        let item = &self.item_data[idx];
+        if self.edit.is_some() {
+            self.show_edit_item(ui);
+            return;
+        }
 
+        let Some(item) =
+            self.active_item.as_ref().and_then(|a| a.data.as_ref())
+        else {
+            return;
+        };
+        let item = item.clone();
+        self.show_refs(ui);
         egui::ScrollArea::both().show(ui, |ui| {
-            for f in self.fields.iter() {
-                ui.label(&f.name);
-                (f.show)(&item[f.range.clone()], ui);
-            }
+            show_schema::show_model(&self.schema, &item, ui);
         });
     }
 }
@@ -1,17 +1,32 @@
+mod dump;
+mod edit;
+mod export;
 mod field;
+mod filter;
 mod item;
+mod page;
+mod refs;
+mod sort;
 
-use field::Field;
+pub use dump::DumpFormat;
+use edit::{EditMode, Journal};
+use export::Export;
+pub(super) use field::Field;
+use filter::FilterScan;
+use page::PageCache;
+use refs::RefScan;
+use sort::{SortIndex, SortKey};
 
-use super::{Database, Value};
+use super::{Database, TileState};
 use crate::utils::db_name;
+use crate::value::NumberStats;
 use crate::Result;
 use shah::db::entity::{EntityHead, EntityKochProg, ENTITY_META};
 use shah::models::{Binary, Schema, SchemaModel};
 use shah::DbError;
 use std::fs::File;
 use std::io::{Seek, SeekFrom};
-use std::ops::DerefMut;
+use std::ops::Range;
 use std::{fs::OpenOptions, os::unix::fs::FileExt, path::PathBuf};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
@@ -19,11 +34,16 @@ struct EntityPersist {
     field_visibility: Vec<(String, bool)>,
 }
 
+pub struct ActiveItem {
+    pub id: u64,
+    /// None until the row is read
+    pub data: Option<Vec<u8>>,
+}
+
 pub struct EntityDb {
     pub file: File,
-    //path: PathBuf,
+    path: PathBuf,
     pub name: String,
-    #[allow(dead_code)]
     pub scope: String,
     pub prefix: String,
     pub revision: u16,
@@ -31,11 +51,35 @@ pub struct EntityDb {
     pub koch_prog: EntityKochProg,
     pub item_size: u64,
     pub item_total: u64,
-    pub item_skip: Value<u64>,
-    pub item_show: Value<u64>,
-    pub item_data: Vec<Vec<u8>>,
-    pub sort_by: Option<Field>,
-    active_item: Option<usize>,
+    /// item_total when the database was opened
+    open_total: u64,
+    /// keep the last items in view as they are appended
+    follow_tail: bool,
+    /// items were appended in the last reload, the filter and the sort
+    /// are extended over them in the next frame
+    grew: bool,
+    pages: PageCache,
+    /// table rows visible in the last frame
+    visible: Range<u64>,
+    /// table row to scroll to in the next frame
+    scroll_to: Option<u64>,
+    goto_id: String,
+    goto_error: Option<String>,
+    load_error: Option<String>,
+    sort_keys: Vec<SortKey>,
+    sort: Option<SortIndex>,
+    filter_query: String,
+    filter_error: Option<String>,
+    filter: Option<FilterScan>,
+    active_item: Option<ActiveItem>,
+    /// references to the active item found in the other databases
+    refs: Option<RefScan>,
+    edit: Option<EditMode>,
+    /// edits written in this session, newest last
+    journal: Vec<Journal>,
+    edit_confirm: bool,
+    edit_error: Option<String>,
+    export: Export,
     pub fields: Vec<Field>,
     read_from_mem: bool,
     id: egui::Id,
@@ -80,6 +124,52 @@ impl Database for EntityDb {
     fn init(path: PathBuf) -> Result<Self> {
         Self::init(path)
     }
+    fn reload(&mut self) {
+        self.reload()
+    }
+    fn state(&self) -> TileState {
+        let sort = self.sort_keys.iter().map(|k| {
+            let name = &self.fields[k.field].key;
+            if k.desc {
+                format!("-{name}")
+            } else {
+                name.clone()
+            }
+        });
+        let hidden = self.fields.iter().any(|f| !f.visible);
+        let fields = self.fields.iter().filter(|f| f.visible);
+        TileState {
+            skip: Some(self.scroll_to.unwrap_or(self.visible.start)),
+            sort: sort.collect(),
+            fields: hidden.then(|| fields.map(|f| f.key.clone()).collect()),
+            filter: self.filter_query.clone(),
+            ..Default::default()
+        }
+    }
+    fn restore(&mut self, state: &TileState, ctx: &egui::Context) {
+        if let Some(names) = &state.fields {
+            for f in self.fields.iter_mut() {
+                f.visible = names.contains(&f.key);
+            }
+            // the workspace wins over the visibility kept by egui
+            self.read_from_mem = false;
+        }
+        self.sort_keys = state
+            .sort
+            .iter()
+            .filter_map(|s| {
+                let (name, desc) = match s.strip_prefix('-') {
+                    Some(name) => (name, true),
+                    None => (s.as_str(), false),
+                };
+                let field = self.fields.iter().position(|f| f.key == name)?;
+                Some(SortKey { field, desc })
+            })
+            .collect();
+        self.filter_query = state.filter.clone();
+        self.apply_filter(ctx.clone());
+        self.scroll_to = state.skip;
+    }
 }
 
 impl EntityDb {
@@ -95,57 +185,66 @@ impl EntityDb {
         let file = OpenOptions::new().read(true).open(&path)?;
         let mut head = EntityHead::default();
         file.read_exact_at(head.as_binary_mut(), 0)?;
+        if head.item_size == 0 {
+            return Err(DbError::InvalidDbHead)?;
+        }
 
         let schema = match Schema::decode(&head.schema)? {
             Schema::Model(m) => m,
             _ => return Err(DbError::InvalidDbSchema)?,
         };
-
-        let mut fields = Vec::<Field>::with_capacity(schema.fields.len());
-        let mut i = 0usize;
-        for (fdx, (fi, fs)) in schema.fields.iter().enumerate() {
-            let s = fs.size();
-            let (show, show_array) = Field::get_show(fs);
-            let range = i..i + s;
-            fields.push(Field {
-                idx: fdx,
-                name: format!("{fi}: {}", Field::get_ty(fs)),
-                number_stats: Field::get_number_stats(fs),
-                number_sort: Field::get_number_sort(fs),
-                range,
-                show,
-                show_array,
-                visible: true,
-            });
-            i += s;
+        // the fields are sliced out of every item
+        if head.item_size < schema.size {
+            return Err(DbError::InvalidDbSchema)?;
         }
 
+        let fields = Field::from_model(&schema);
+
         let (scope, prefix, _name) = db_name(&path);
         let head_name = head.db_head.name().to_string();
         let prefix = if head_name == prefix { "" } else { prefix };
 
+        let pages = PageCache::new(file.try_clone()?, head.item_size);
+
         let mut db = Self {
             file,
-            // path,
             name: head_name,
             prefix: prefix.to_string(),
             scope: scope.to_string(),
             revision: head.db_head.revision,
             item_size: head.item_size,
-            item_skip: Value::new(0),
-            item_show: Value::new(10),
-            item_data: Vec::with_capacity(10),
+            pages,
+            visible: 0..0,
+            scroll_to: None,
+            goto_id: String::new(),
+            goto_error: None,
+            load_error: None,
             item_total: 0,
+            open_total: 0,
+            follow_tail: false,
+            grew: false,
             active_item: None,
+            refs: None,
+            edit: None,
+            journal: Vec::new(),
+            edit_confirm: false,
+            edit_error: None,
+            export: Export::default(),
             koch_prog: Default::default(),
-            sort_by: None,
+            sort_keys: Vec::new(),
+            sort: None,
+            filter_query: String::new(),
+            filter_error: None,
+            filter: None,
             fields,
             schema,
             read_from_mem: true,
-            id: egui::Id::new(("entity", path)),
+            id: egui::Id::new(("entity", &path)),
+            path,
         };
 
         db.update();
+        db.open_total = db.item_total;
 
         Ok(db)
     }
@@ -157,20 +256,85 @@ impl EntityDb {
     }
 
     pub fn update_total(&mut self) {
-        let pos = self.file.seek(SeekFrom::End(0)).expect("no seek");
-        self.item_total = (pos - ENTITY_META) / self.item_size;
+        match self.file.seek(SeekFrom::End(0)) {
+            Ok(pos) => {
+                self.item_total =
+                    pos.saturating_sub(ENTITY_META) / self.item_size;
+            }
+            Err(e) => self.load_error = Some(format!("could not seek: {e}")),
+        }
+    }
+
+    pub fn reload(&mut self) {
+        let total = self.item_total;
+        self.update_total();
+        self.grew |= self.item_total > total;
+        self.update_koch_prog();
+        self.pages.invalidate();
+        if self.follow_tail {
+            self.jump_to_tail();
+        }
+    }
+
+    fn jump_to_tail(&mut self) {
+        self.scroll_to = Some(self.row_total().saturating_sub(1));
     }
 
     pub fn update_koch_prog(&mut self) {
-        self.file
-            .read_exact_at(self.koch_prog.as_binary_mut(), EntityHead::N)
-            .expect("could not read");
+        let buf = self.koch_prog.as_binary_mut();
+        if let Err(e) = self.file.read_exact_at(buf, EntityHead::N) {
+            self.load_error = Some(format!("could not read koch prog: {e}"));
+        }
     }
 
-    pub fn show(&mut self, ui: &mut egui::Ui) {
-        if self.item_skip.changed() || self.item_show.changed() {
-            self.update_items();
+    /// number of rows in the table
+    fn row_total(&self) -> u64 {
+        match self.row_ids() {
+            Some(ids) => ids.len() as u64,
+            None => self.item_total,
+        }
+    }
+
+    /// scans the items appended since the filter and the sort were built
+    fn extend_rows(&mut self, ctx: egui::Context) {
+        match &mut self.filter {
+            // the sort is extended once the scan is done
+            Some(scan) => {
+                if let Err(e) = scan.extend(self.item_total, ctx) {
+                    scan.error = Some(e.to_string());
+                }
+            }
+            None if !self.sort_keys.is_empty() => self.extend_sort(ctx),
+            None => {}
         }
+    }
+
+    /// collects the results of the background work
+    fn poll(&mut self, ctx: &egui::Context) {
+        self.pages.poll();
+        if std::mem::take(&mut self.grew) {
+            self.extend_rows(ctx.clone());
+        }
+        let mut filter_done = false;
+        if let Some(scan) = &mut self.filter {
+            let was_done = scan.done();
+            if scan.poll() {
+                self.pages.invalidate_partial();
+            }
+            filter_done = !was_done && scan.done();
+        }
+        if filter_done && !self.sort_keys.is_empty() {
+            self.extend_sort(ctx.clone());
+        }
+        if let Some(sort) = &mut self.sort {
+            if sort.poll() {
+                self.update_items();
+            }
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        self.poll(ui.ctx());
         ui.horizontal_wrapped(|ui| {
             ui.label(format!("db: {}.{}", self.name, self.revision));
             ui.label(format!(
@@ -182,28 +346,44 @@ impl EntityDb {
                 self.koch_prog.prog, self.koch_prog.total
             ));
             ui.label(format!("total items: {}", self.item_total));
+            if ui
+                .small_button("check")
+                .on_hover_text("run an integrity check")
+                .clicked()
+            {
+                super::nav::push(
+                    ui.ctx(),
+                    super::nav::Request::Check(self.path.clone()),
+                );
+            }
+            if self.pages.busy() {
+                ui.spinner();
+            }
+            if self.item_total > self.open_total {
+                ui.colored_label(
+                    egui::Color32::LIGHT_GREEN,
+                    format!(
+                        "+{} since open",
+                        self.item_total - self.open_total
+                    ),
+                );
+            }
         });
+        for e in [&self.load_error, &self.pages.error].into_iter().flatten() {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+        self.show_filter(ui);
+        self.show_sort(ui);
+        self.show_export(ui);
+        self.show_edit_toggle(ui);
+        self.show_journal(ui);
         ui.horizontal(|ui| {
-            ui.add(
-                egui::Slider::new(
-                    self.item_skip.deref_mut(),
-                    0..=self.item_total,
-                )
-                .text("skip"),
-            );
+            self.show_goto(ui);
             ui.separator();
-            ui.add(
-                egui::Slider::new(
-                    self.item_show.deref_mut(),
-                    0..=self.item_total,
-                )
-                .text("show"),
-            );
-            if let Some(fsb) = &self.sort_by {
-                ui.separator();
-                if ui.button(format!("sort by: {}", fsb.name)).clicked() {
-                    self.sort_by = None;
-                }
+            if ui.checkbox(&mut self.follow_tail, "follow tail").changed()
+                && self.follow_tail
+            {
+                self.jump_to_tail();
             }
         });
         ui.separator();
@@ -213,17 +393,17 @@ impl EntityDb {
             }
         });
         ui.separator();
+        let visible = self.visible_data();
         ui.vertical(|ui| {
             for f in self.fields.iter() {
-                if !f.visible {
+                if !f.visible || !f.is_number() {
                     continue;
                 }
-                let Some(ns) = f.number_stats else { continue };
-                ui.label(format!(
-                    "{}: {}",
-                    f.name,
-                    ns(&self.item_data, f.range.clone())
-                ));
+                let mut stats = NumberStats::default();
+                for item in visible.iter() {
+                    stats.add(&f.decode(&item[f.range.clone()]));
+                }
+                ui.label(format!("{}: {stats}", f.name));
             }
         });
         let colps = egui::CollapsingHeader::new("schema").show_background(true);
@@ -240,3 +420,78 @@ impl EntityDb {
         self.show_active_item(ui);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, snapshot};
+
+    #[test]
+    fn show() {
+        let mut db = EntityDb::init(fixtures::get().item.clone()).unwrap();
+        let text = snapshot::render(|ui| Database::show(&mut db, ui));
+        snapshot::check("entity_show", &text);
+    }
+
+    #[test]
+    fn show_active_item() {
+        let mut db = EntityDb::init(fixtures::get().item.clone()).unwrap();
+        let text = snapshot::render(|ui| {
+            if db.active_item.is_none() {
+                db.goto(3);
+            }
+            Database::show(&mut db, ui)
+        });
+        snapshot::check("entity_active_item", &text);
+    }
+
+    #[test]
+    fn zero_item_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("item.0.shah");
+        std::fs::copy(&fixtures::get().item, &path).unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&path);
+        let file = file.unwrap();
+        let mut head = EntityHead::default();
+        file.read_exact_at(head.as_binary_mut(), 0).unwrap();
+        head.item_size = 0;
+        file.write_all_at(head.as_binary(), 0).unwrap();
+        assert!(EntityDb::init(path).is_err());
+    }
+
+    #[test]
+    fn reload_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("item.0.shah");
+        std::fs::copy(&fixtures::get().item, &path).unwrap();
+        let ctx = egui::Context::default();
+        let mut db = EntityDb::init(path.clone()).unwrap();
+        let age = db.fields.iter().position(|f| f.key == "age").unwrap();
+        db.sort_keys = vec![SortKey { field: age, desc: true }];
+        db.filter_query = "age >= 20".to_string();
+        db.apply_filter(ctx.clone());
+        let wait = |db: &mut EntityDb| loop {
+            db.poll(&ctx);
+            let scanned = db.filter.as_ref().is_some_and(|s| s.done());
+            if scanned && db.sort.as_ref().is_some_and(|s| s.ready) {
+                break;
+            }
+            std::thread::yield_now();
+        };
+        wait(&mut db);
+        assert_eq!(db.row_ids(), Some(&[4, 3, 2][..]));
+
+        // a copy of the item with age 30
+        let mut item = vec![0u8; db.item_size as usize];
+        let total = db.item_total;
+        let pos = ENTITY_META + 3 * db.item_size;
+        db.file.read_exact_at(&mut item, pos).unwrap();
+        let file = OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut &file, &item).unwrap();
+        db.reload();
+        assert_eq!(db.item_total, total + 1);
+        wait(&mut db);
+        assert_eq!(db.filter.as_ref().unwrap().matched, [2, 3, 4, total]);
+        assert_eq!(db.row_ids(), Some(&[4, 3, total, 2][..]));
+    }
+}
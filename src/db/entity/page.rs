@@ -0,0 +1,187 @@
+use super::item::{read_rows, Rows};
+use crate::db::worker::Worker;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::ops::Range;
+
+/// rows per cached page
+pub const PAGE_ROWS: u64 = 256;
+/// how many pages are kept in memory
+const CACHE_PAGES: usize = 64;
+/// pages read ahead of and behind the visible rows
+const PREFETCH: u64 = 1;
+
+struct Page {
+    ids: Vec<u64>,
+    data: Vec<Vec<u8>>,
+    /// the file changed since this page was read
+    stale: bool,
+}
+
+type PageReq = (u64, Vec<(u64, Vec<u64>)>);
+type PageRes = (u64, Vec<(u64, Rows)>);
+
+/// LRU cache of table rows, grouped in pages of [`PAGE_ROWS`].
+/// a page holds rows in table order, which is the sort or filter order
+/// when one is active
+pub struct PageCache {
+    pages: HashMap<u64, Page>,
+    /// least recently used first
+    lru: VecDeque<u64>,
+    /// bumped whenever the rows change, responses of older epochs are
+    /// dropped
+    epoch: u64,
+    /// pages of the last request that did not arrive yet
+    requested: Vec<u64>,
+    worker: Worker<PageReq, PageRes>,
+    pub error: Option<String>,
+}
+
+impl PageCache {
+    pub fn new(file: File, item_size: u64) -> Self {
+        let worker = Worker::start(move |(epoch, pages): PageReq| {
+            let pages = pages
+                .into_iter()
+                .map(|(page, ids)| (page, read_rows(&file, item_size, ids)))
+                .collect();
+            (epoch, pages)
+        });
+        Self {
+            pages: HashMap::new(),
+            lru: VecDeque::new(),
+            epoch: 0,
+            requested: Vec::new(),
+            worker,
+            error: None,
+        }
+    }
+
+    /// the rows now map to other items, drops every page
+    pub fn clear(&mut self) {
+        self.epoch += 1;
+        self.pages.clear();
+        self.lru.clear();
+        self.requested.clear();
+    }
+
+    /// the file changed. pages are shown until they are read again
+    pub fn invalidate(&mut self) {
+        self.epoch += 1;
+        self.requested.clear();
+        for page in self.pages.values_mut() {
+            page.stale = true;
+        }
+    }
+
+    /// more rows were appended, only pages that were not full are read again
+    pub fn invalidate_partial(&mut self) {
+        self.epoch += 1;
+        self.requested.clear();
+        for page in self.pages.values_mut() {
+            if (page.ids.len() as u64) < PAGE_ROWS {
+                page.stale = true;
+            }
+        }
+    }
+
+    pub fn busy(&self) -> bool {
+        self.worker.busy()
+    }
+
+    /// id and bytes of the item shown at `row`
+    pub fn row(&self, row: u64) -> Option<(u64, &[u8])> {
+        let page = self.pages.get(&(row / PAGE_ROWS))?;
+        let i = (row % PAGE_ROWS) as usize;
+        Some((*page.ids.get(i)?, page.data.get(i)?))
+    }
+
+    pub fn find(&self, id: u64) -> Option<&[u8]> {
+        self.pages.values().find_map(|page| {
+            let i = page.ids.iter().position(|x| *x == id)?;
+            Some(page.data[i].as_slice())
+        })
+    }
+
+    /// replaces the cached bytes of an item after it was written
+    pub fn set(&mut self, id: u64, data: &[u8]) {
+        for page in self.pages.values_mut() {
+            for (x, item) in page.ids.iter().zip(page.data.iter_mut()) {
+                if *x == id {
+                    item.copy_from_slice(data);
+                }
+            }
+        }
+    }
+
+    pub fn poll(&mut self) {
+        for (epoch, pages) in self.worker.drain() {
+            if epoch != self.epoch {
+                continue;
+            }
+            for (page, rows) in pages {
+                match rows {
+                    Ok((ids, data)) => {
+                        self.requested.retain(|p| *p != page);
+                        self.insert(page, Page { ids, data, stale: false });
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, page: u64, value: Page) {
+        self.pages.insert(page, value);
+        self.touch(page);
+        while self.lru.len() > CACHE_PAGES {
+            let Some(old) = self.lru.pop_front() else { break };
+            self.pages.remove(&old);
+        }
+    }
+
+    fn touch(&mut self, page: u64) {
+        self.lru.retain(|p| *p != page);
+        self.lru.push_back(page);
+    }
+
+    /// makes sure the pages of `rows` are cached, reading the missing ones.
+    /// `ids` maps rows to item ids, None means row and id are the same
+    pub fn fetch(
+        &mut self, rows: Range<u64>, ids: Option<&[u64]>, total: u64,
+        ctx: &egui::Context,
+    ) {
+        if total == 0 {
+            return;
+        }
+        let last = (total - 1) / PAGE_ROWS;
+        let first = (rows.start / PAGE_ROWS).saturating_sub(PREFETCH);
+        let end = (rows.end.saturating_sub(1) / PAGE_ROWS + PREFETCH).min(last);
+
+        let mut missing = Vec::new();
+        for page in first..=end {
+            match self.pages.get(&page) {
+                Some(p) if !p.stale => self.touch(page),
+                _ => missing.push(page),
+            }
+        }
+        if missing.is_empty() || missing == self.requested {
+            return;
+        }
+
+        let req = missing
+            .iter()
+            .map(|page| {
+                let start = page * PAGE_ROWS;
+                let end = (start + PAGE_ROWS).min(total);
+                let page_ids = match ids {
+                    Some(ids) => ids[start as usize..end as usize].to_vec(),
+                    None => (start..end).collect(),
+                };
+                (*page, page_ids)
+            })
+            .collect();
+        self.requested = missing;
+        self.worker.request((self.epoch, req), ctx);
+    }
+}
@@ -0,0 +1,251 @@
+use super::filter::SCAN_BATCH;
+use super::EntityDb;
+use crate::db::nav::{self, Link};
+use crate::utils::db_name;
+use shah::db::entity::ENTITY_META;
+use shah::models::{Binary, Gene, Schema};
+use std::io::{Seek, SeekFrom};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+/// a gene field of some item that points at the scanned item
+pub struct Ref {
+    pub path: PathBuf,
+    pub title: String,
+    pub id: u64,
+    pub field: String,
+    pub link: Link,
+}
+
+enum RefMsg {
+    Found(Vec<Ref>),
+    /// a database was scanned, with the error that stopped it if any
+    Done(Option<String>),
+}
+
+/// scans every gene field of every entity database in a scope for genes
+/// pointing at one item
+pub struct RefScan {
+    /// the item the references point to
+    pub id: u64,
+    pub refs: Vec<Ref>,
+    pub scanned: usize,
+    pub total: usize,
+    pub errors: Vec<String>,
+    rx: Receiver<RefMsg>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl RefScan {
+    /// `own` is the path of the item's database, the item's own gene in
+    /// it is not a reference
+    pub fn start(
+        id: u64, gene: Gene, own: PathBuf, paths: Vec<PathBuf>,
+        ctx: egui::Context,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stop = cancel.clone();
+        let total = paths.len();
+
+        std::thread::spawn(move || {
+            for path in paths {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let mut found = Vec::new();
+                let res = scan(&path, &gene, &own, id, &stop, &mut found);
+                if !found.is_empty() && tx.send(RefMsg::Found(found)).is_err() {
+                    break;
+                }
+                let err = res.err().map(|e| format!("{path:?}: {e}"));
+                if tx.send(RefMsg::Done(err)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+
+        Self {
+            id,
+            refs: Vec::new(),
+            scanned: 0,
+            total,
+            errors: Vec::new(),
+            rx,
+            cancel,
+        }
+    }
+
+    pub fn poll(&mut self) {
+        loop {
+            match self.rx.try_recv() {
+                Ok(RefMsg::Found(refs)) => self.refs.extend(refs),
+                Ok(RefMsg::Done(err)) => {
+                    self.scanned += 1;
+                    self.errors.extend(err);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.scanned = self.total;
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.scanned >= self.total
+    }
+}
+
+impl Drop for RefScan {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// collects the references to `gene` in the database at `path`. files that
+/// are not entities or have no gene fields are skipped
+fn scan(
+    path: &Path, gene: &Gene, own: &Path, own_id: u64, stop: &AtomicBool,
+    found: &mut Vec<Ref>,
+) -> std::io::Result<()> {
+    let Some((mut file, head, model)) = nav::open_entity(path) else {
+        return Ok(());
+    };
+    let mut fields = Vec::new();
+    nav::gene_fields(&Schema::Model(model), "", 0, &mut fields);
+    if fields.is_empty() {
+        return Ok(());
+    }
+
+    let (scope, db, kind) = db_name(path);
+    let title = format!("{scope}/{db}/{kind}");
+    let item_size = head.item_size;
+    let total =
+        file.seek(SeekFrom::End(0))?.saturating_sub(ENTITY_META) / item_size;
+    let mut buf = vec![0u8; (SCAN_BATCH * item_size) as usize];
+    let mut g = Gene::default();
+    let mut id = 0u64;
+    while id < total && !stop.load(Ordering::Relaxed) {
+        let n = SCAN_BATCH.min(total - id);
+        let buf = &mut buf[..(n * item_size) as usize];
+        file.read_exact_at(buf, ENTITY_META + id * item_size)?;
+        for (i, item) in buf.chunks(item_size as usize).enumerate() {
+            let item_id = id + i as u64;
+            for (field, offset) in fields.iter() {
+                let v = &item[*offset..*offset + Gene::S];
+                g.as_binary_mut().copy_from_slice(v);
+                if g.id != gene.id || g.pepper != gene.pepper {
+                    continue;
+                }
+                if path == own && item_id == own_id && field == "gene" {
+                    continue;
+                }
+                let link =
+                    if g.iter == gene.iter { Link::Valid } else { Link::Stale };
+                found.push(Ref {
+                    path: path.to_path_buf(),
+                    title: title.clone(),
+                    id: item_id,
+                    field: field.clone(),
+                    link,
+                });
+            }
+        }
+        id += n;
+    }
+    Ok(())
+}
+
+impl EntityDb {
+    /// the `gene` field of the active item
+    fn active_gene(&self) -> Option<Gene> {
+        let data = self.active_item.as_ref()?.data.as_ref()?;
+        let offset = nav::gene_offset(&self.schema)?;
+        let mut g = Gene::default();
+        g.as_binary_mut().copy_from_slice(&data[offset..offset + Gene::S]);
+        Some(g)
+    }
+
+    pub(super) fn show_refs(&mut self, ui: &mut egui::Ui) {
+        let Some(active) = &self.active_item else { return };
+        let id = active.id;
+        if self.refs.as_ref().is_some_and(|r| r.id != id) {
+            self.refs = None;
+        }
+        if let Some(refs) = &mut self.refs {
+            refs.poll();
+        }
+
+        ui.horizontal(|ui| {
+            let gene = self.active_gene().filter(|g| g.is_some());
+            let res = ui
+                .add_enabled(
+                    gene.is_some(),
+                    egui::Button::new("find references"),
+                )
+                .on_disabled_hover_text("the item has no gene");
+            if res.clicked() {
+                if let Some(gene) = gene {
+                    let mut paths = nav::scope_paths(ui.ctx(), &self.scope);
+                    if !paths.contains(&self.path) {
+                        paths.push(self.path.clone());
+                    }
+                    let own = self.path.clone();
+                    let ctx = ui.ctx().clone();
+                    self.refs = Some(RefScan::start(id, gene, own, paths, ctx));
+                }
+            }
+            let Some(refs) = &self.refs else { return };
+            if !refs.done() {
+                ui.spinner();
+            }
+            ui.label(format!(
+                "references: {} | databases: {}/{}",
+                refs.refs.len(),
+                refs.scanned,
+                refs.total
+            ));
+            if ui.small_button("x").clicked() {
+                self.refs = None;
+            }
+        });
+
+        let Some(refs) = &self.refs else { return };
+        for e in refs.errors.iter() {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+        if refs.refs.is_empty() {
+            return;
+        }
+        egui::ScrollArea::vertical().id_salt("refs").max_height(150.0).show(
+            ui,
+            |ui| {
+                for r in refs.refs.iter() {
+                    ui.horizontal(|ui| {
+                        let mut text = egui::RichText::new(format!(
+                            "{} #{} .{}",
+                            r.title, r.id, r.field
+                        ));
+                        if let Some(c) = r.link.color(ui.visuals()) {
+                            text = text.color(c);
+                        }
+                        let res =
+                            ui.link(text).on_hover_text(r.link.describe());
+                        if res.clicked() {
+                            nav::push(
+                                ui.ctx(),
+                                nav::Request::Open(r.path.clone(), r.id),
+                            );
+                        }
+                    });
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,392 @@
+use super::filter::FilterScan;
+use super::EntityDb;
+use crate::value::{decode_str, Decoded};
+use shah::db::entity::ENTITY_META;
+use shah::models::Schema;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::ops::Range;
+use std::os::unix::fs::FileExt;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+/// how many items are read from disk at once while indexing
+const SORT_BATCH: u64 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortKey {
+    /// index into [`EntityDb::fields`]
+    pub field: usize,
+    pub desc: bool,
+}
+
+enum SortMsg {
+    Progress(u64),
+    Done(Vec<u64>),
+    Error(String),
+}
+
+/// ids of the whole file (or of a filter result) in sorted order,
+/// built on a background thread. only the bytes of the key fields are
+/// kept while indexing, that is their size plus 8 bytes per row
+pub struct SortIndex {
+    pub ids: Option<Vec<u64>>,
+    /// false while indexing, `ids` may hold the previous order meanwhile
+    pub ready: bool,
+    pub scanned: u64,
+    pub total: u64,
+    pub error: Option<String>,
+    rx: Receiver<SortMsg>,
+    cancel: Arc<AtomicBool>,
+}
+
+type KeyPart = (Range<usize>, Arc<Schema>, bool);
+
+fn compare_part(schema: &Schema, a: &[u8], b: &[u8]) -> Ordering {
+    match schema {
+        // compared in place, decoding would copy every string
+        Schema::Array { is_str: true, .. } => decode_str(a).cmp(decode_str(b)),
+        _ => Decoded::decode(schema, a).total_cmp(&Decoded::decode(schema, b)),
+    }
+}
+
+/// compares the key bytes of two rows, `parts` are their ranges in the key
+fn compare(parts: &[KeyPart], a: &[u8], b: &[u8]) -> Ordering {
+    for (range, schema, desc) in parts {
+        let o = compare_part(schema, &a[range.clone()], &b[range.clone()]);
+        if o.is_ne() {
+            return if *desc { o.reverse() } else { o };
+        }
+    }
+    Ordering::Equal
+}
+
+impl SortIndex {
+    /// sorts `ids`, or every item of the file when `ids` is None
+    pub fn start(
+        file: &File, keys: Vec<KeyPart>, item_size: u64, total: u64,
+        ids: Option<Vec<u64>>, ctx: egui::Context,
+    ) -> std::io::Result<Self> {
+        let file = file.try_clone()?;
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stop = cancel.clone();
+        let total = ids.as_ref().map(|v| v.len() as u64).unwrap_or(total);
+
+        // where each key field sits in the item and in the key of a row
+        let mut stride = 0;
+        let mut parts = Vec::with_capacity(keys.len());
+        let mut fields = Vec::with_capacity(keys.len());
+        for (range, schema, desc) in keys {
+            let len = range.len();
+            parts.push((stride..stride + len, schema, desc));
+            fields.push(range);
+            stride += len;
+        }
+        let copy = move |item: &[u8], out: &mut Vec<u8>| {
+            for range in fields.iter() {
+                out.extend_from_slice(&item[range.clone()]);
+            }
+        };
+
+        std::thread::spawn(move || {
+            let size = item_size as usize;
+            let mut keys = Vec::with_capacity(total as usize * stride);
+            let mut buf = vec![0u8; SORT_BATCH as usize * size];
+            let mut done = 0u64;
+            while done < total {
+                if stop.load(AtomicOrdering::Relaxed) {
+                    return;
+                }
+                let n = SORT_BATCH.min(total - done);
+                let res = match &ids {
+                    Some(ids) => {
+                        let buf = &mut buf[..size];
+                        ids[done as usize..(done + n) as usize]
+                            .iter()
+                            .try_for_each(|id| {
+                                let pos = ENTITY_META + id * item_size;
+                                file.read_exact_at(buf, pos)?;
+                                copy(buf, &mut keys);
+                                Ok(())
+                            })
+                    }
+                    None => {
+                        let buf = &mut buf[..n as usize * size];
+                        let pos = ENTITY_META + done * item_size;
+                        file.read_exact_at(buf, pos).map(|_| {
+                            for item in buf.chunks(size) {
+                                copy(item, &mut keys);
+                            }
+                        })
+                    }
+                };
+                if let Err(e) = res {
+                    let _ = tx.send(SortMsg::Error(e.to_string()));
+                    return;
+                }
+                done += n;
+                if tx.send(SortMsg::Progress(done)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+
+            let key = |i: u64| {
+                let at = i as usize * stride;
+                &keys[at..at + stride]
+            };
+            let mut order = (0..total).collect::<Vec<_>>();
+            order.sort_by(|a, b| compare(&parts, key(*a), key(*b)));
+            if let Some(ids) = &ids {
+                for i in order.iter_mut() {
+                    *i = ids[*i as usize];
+                }
+            }
+            let _ = tx.send(SortMsg::Done(order));
+            ctx.request_repaint();
+        });
+
+        Ok(Self {
+            ids: None,
+            ready: false,
+            scanned: 0,
+            total,
+            error: None,
+            rx,
+            cancel,
+        })
+    }
+
+    /// collects the progress sent so far. returns true once the ids are ready
+    pub fn poll(&mut self) -> bool {
+        loop {
+            match self.rx.try_recv() {
+                Ok(SortMsg::Progress(scanned)) => self.scanned = scanned,
+                Ok(SortMsg::Done(ids)) => {
+                    self.ids = Some(ids);
+                    self.ready = true;
+                    return true;
+                }
+                Ok(SortMsg::Error(e)) => self.error = Some(e),
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => {
+                    return false
+                }
+            }
+        }
+    }
+}
+
+impl Drop for SortIndex {
+    fn drop(&mut self) {
+        self.cancel.store(true, AtomicOrdering::Relaxed);
+    }
+}
+
+/// rows in sorted order once the index is ready, otherwise the filter
+/// result. None means every item of the file in id order
+pub(super) fn row_ids<'a>(
+    sort: &'a Option<SortIndex>, filter: &'a Option<FilterScan>,
+) -> Option<&'a [u64]> {
+    if let Some(ids) = sort.as_ref().and_then(|s| s.ids.as_ref()) {
+        return Some(ids);
+    }
+    filter.as_ref().map(|scan| scan.matched.as_slice())
+}
+
+impl EntityDb {
+    pub(super) fn row_ids(&self) -> Option<&[u64]> {
+        row_ids(&self.sort, &self.filter)
+    }
+
+    /// click on a column header. `append` (shift+click) adds the column
+    /// as another key instead of replacing the current ones
+    pub(super) fn toggle_sort(
+        &mut self, field: usize, append: bool, ctx: egui::Context,
+    ) {
+        match self.sort_keys.iter().position(|k| k.field == field) {
+            Some(i) if append || self.sort_keys.len() == 1 => {
+                self.sort_keys[i].desc = !self.sort_keys[i].desc;
+            }
+            _ if !append => {
+                self.sort_keys = vec![SortKey { field, desc: false }];
+            }
+            _ => self.sort_keys.push(SortKey { field, desc: false }),
+        }
+        self.restart_sort(ctx);
+    }
+
+    pub(super) fn restart_sort(&mut self, ctx: egui::Context) {
+        self.sort = None;
+        self.active_item = None;
+        // unsorted rows are shown until the index is ready
+        self.update_items();
+        self.sort = self.start_sort(ctx);
+    }
+
+    /// sorts again after items were appended. the previous order stays in
+    /// view until the new index is ready
+    pub(super) fn extend_sort(&mut self, ctx: egui::Context) {
+        let Some(ids) = self.sort.as_mut().and_then(|s| s.ids.take()) else {
+            return self.restart_sort(ctx);
+        };
+        self.sort = self.start_sort(ctx);
+        if let Some(sort) = &mut self.sort {
+            sort.ids = Some(ids);
+        }
+    }
+
+    fn start_sort(&self, ctx: egui::Context) -> Option<SortIndex> {
+        if self.sort_keys.is_empty() {
+            return None;
+        }
+        // started again from show once the filter scan is finished
+        if self.filter.as_ref().is_some_and(|scan| !scan.done()) {
+            return None;
+        }
+
+        let keys = self
+            .sort_keys
+            .iter()
+            .map(|k| {
+                let f = &self.fields[k.field];
+                (f.range.clone(), f.schema.clone(), k.desc)
+            })
+            .collect::<Vec<_>>();
+        let ids = self.filter.as_ref().map(|scan| scan.matched.clone());
+        match SortIndex::start(
+            &self.file,
+            keys,
+            self.item_size,
+            self.item_total,
+            ids,
+            ctx,
+        ) {
+            Ok(sort) => Some(sort),
+            Err(e) => {
+                log::error!("could not start sorting: {e}");
+                None
+            }
+        }
+    }
+
+    pub(super) fn show_sort(&mut self, ui: &mut egui::Ui) {
+        if self.sort_keys.is_empty() {
+            return;
+        }
+        ui.horizontal_wrapped(|ui| {
+            let keys = self
+                .sort_keys
+                .iter()
+                .map(|k| {
+                    let arrow = if k.desc { "▼" } else { "▲" };
+                    format!("{} {arrow}", self.fields[k.field].name)
+                })
+                .collect::<Vec<_>>();
+            ui.label(format!("sort by: {}", keys.join(", ")));
+            if ui.button("clear").clicked() {
+                self.sort_keys.clear();
+                self.restart_sort(ui.ctx().clone());
+                return;
+            }
+
+            match &self.sort {
+                Some(sort) if !sort.ready => {
+                    if sort.error.is_none() {
+                        ui.spinner();
+                    }
+                    ui.label(format!(
+                        "indexed: {}/{}",
+                        sort.scanned, sort.total
+                    ));
+                    if let Some(e) = &sort.error {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    ui.spinner();
+                    ui.label("waiting for filter");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn sorted(keys: &[(&str, bool)], ids: Option<Vec<u64>>) -> Vec<u64> {
+        sorted_in(fixtures::get().item.clone(), keys, ids)
+    }
+
+    /// the ids of the items at `path` sorted by `keys` of (field, desc)
+    fn sorted_in(
+        path: std::path::PathBuf, keys: &[(&str, bool)], ids: Option<Vec<u64>>,
+    ) -> Vec<u64> {
+        let db = EntityDb::init(path).unwrap();
+        let keys = keys
+            .iter()
+            .map(|(name, desc)| {
+                let f = db.fields.iter().find(|f| f.key == *name).unwrap();
+                (f.range.clone(), f.schema.clone(), *desc)
+            })
+            .collect();
+        let ctx = egui::Context::default();
+        let mut sort = SortIndex::start(
+            &db.file,
+            keys,
+            db.item_size,
+            db.item_total,
+            ids,
+            ctx,
+        )
+        .unwrap();
+        while !sort.poll() {
+            assert!(sort.error.is_none(), "{:?}", sort.error);
+            std::thread::yield_now();
+        }
+        sort.ids.take().unwrap()
+    }
+
+    #[test]
+    fn numbers() {
+        // delta is -100 * id, ratio 1 / id and big i64::MIN + id. the
+        // empty item 0 is all zero
+        assert_eq!(sorted(&[("delta", false)], None), [4, 3, 2, 1, 0]);
+        assert_eq!(sorted(&[("delta", true)], None), [0, 1, 2, 3, 4]);
+        assert_eq!(sorted(&[("ratio", false)], None), [0, 4, 3, 2, 1]);
+        assert_eq!(sorted(&[("pos.y", false)], None), [4, 3, 2, 1, 0]);
+        assert_eq!(sorted(&[("big", true)], None), [0, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn keys() {
+        let keys = [("active", true), ("delta", false)];
+        assert_eq!(sorted(&keys, None), [3, 1, 4, 2, 0]);
+        assert_eq!(sorted(&[("name", true)], None), [4, 3, 2, 1, 0]);
+        let ids = Some(vec![1, 2, 4]);
+        assert_eq!(sorted(&[("score", true)], ids), [4, 2, 1]);
+    }
+
+    #[test]
+    fn negative_floats() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("item.0.shah");
+        std::fs::copy(&fixtures::get().item, &path).unwrap();
+        let db = EntityDb::init(path.clone()).unwrap();
+        let f = db.fields.iter().find(|f| f.key == "ratio").unwrap();
+        let file = std::fs::OpenOptions::new().write(true).open(&path);
+        let file = file.unwrap();
+        for (id, v) in [(1, -0.5), (3, f64::NEG_INFINITY), (4, -0.0)] {
+            let pos = ENTITY_META + id * db.item_size + f.range.start as u64;
+            file.write_all_at(&f64::to_le_bytes(v), pos).unwrap();
+        }
+        // -inf, -0.5, -0.0, 0.0 of the empty item, 0.5
+        let order = sorted_in(path, &[("ratio", false)], None);
+        assert_eq!(order, [3, 1, 4, 0, 2]);
+    }
+}
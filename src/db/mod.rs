@@ -1,12 +1,32 @@
+pub mod custom;
 mod entity;
+pub mod nav;
+mod opening;
+mod pond;
+mod raw;
+mod report;
+mod show_schema;
+mod snake;
+mod trie;
 mod trie_const;
+mod watch;
+pub mod worker;
+use crate::error::ShahVisualError;
 use crate::Result;
-use entity::EntityDb;
+use custom::CustomDb;
+pub use entity::{DumpFormat, EntityDb};
+use opening::OpeningDb;
+use pond::PondDb;
+use raw::RawDb;
+use report::ReportDb;
 use shah::models::{Binary, DbHead, ShahMagicDb};
 use shah::DbError;
+use snake::SnakeDb;
 use std::ops::{Deref, DerefMut};
 use std::{fs::OpenOptions, os::unix::fs::FileExt, path::PathBuf};
-use trie_const::TrieConstDb;
+use trie::TrieDb;
+pub use trie_const::TrieConstDb;
+use watch::FileWatch;
 
 pub struct Value<T> {
     main: T,
@@ -45,15 +65,52 @@ impl<T> DerefMut for Value<T> {
     }
 }
 
-trait Database: Sized {
+/// the view of a tile that a workspace keeps. every database fills in
+/// the parts it has and ignores the rest
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(default)]
+pub struct TileState {
+    /// the first row or byte in view
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip: Option<u64>,
+    /// how many rows are listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show: Option<u64>,
+    /// sorted columns by key, a `-` in front sorts one descending
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sort: Vec<String>,
+    /// the keys of the shown columns, every one when None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub filter: String,
+}
+
+pub trait Database: Sized {
     fn init(path: PathBuf) -> Result<Self>;
     fn title(&self) -> String;
     fn show(&mut self, ui: &mut egui::Ui);
+    /// called when one of the database files changed on disk
+    fn reload(&mut self) {}
+    /// the view to keep in a workspace
+    fn state(&self) -> TileState {
+        TileState::default()
+    }
+    /// brings back a view from [`Database::state`]
+    fn restore(&mut self, _state: &TileState, _ctx: &egui::Context) {}
 }
 
 pub struct DbTile {
     pub kind: DatabaseKind,
     pub path: PathBuf,
+    watch: Option<FileWatch>,
 }
 
 impl PartialEq for DbTile {
@@ -63,22 +120,97 @@ impl PartialEq for DbTile {
 }
 
 impl DbTile {
-    pub fn new(path: PathBuf) -> Result<Self> {
-        Ok(Self { kind: DatabaseKind::new(path.clone())?, path })
+    /// opens the database at `path` on a background thread
+    pub fn new(path: PathBuf, ctx: &egui::Context) -> Result<Self> {
+        if !path.is_file() {
+            return Err(ShahVisualError::MissingFile(path));
+        }
+        let kind = DatabaseKind::Opening(Box::new(OpeningDb::start(
+            path.clone(),
+            ctx,
+        )));
+        Ok(Self { kind, path, watch: None })
+    }
+
+    /// the integrity report of the entity file at `path`
+    pub fn report(path: PathBuf) -> Self {
+        let kind = DatabaseKind::Report(ReportDb::new(path.clone()));
+        Self { kind, path, watch: None }
+    }
+
+    pub fn is_report(&self) -> bool {
+        matches!(self.kind, DatabaseKind::Report(_))
     }
 
     pub fn title(&self) -> String {
         self.kind.title()
     }
 
+    /// swaps in the database once it is open. false while it is opening
+    pub fn opened(&mut self, ctx: &egui::Context) -> bool {
+        let DatabaseKind::Opening(db) = &mut self.kind else { return true };
+        match db.poll(ctx) {
+            Some(kind) => {
+                self.kind = kind;
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn show(&mut self, ui: &mut egui::Ui) {
+        if !self.opened(ui.ctx()) {
+            self.kind.show(ui);
+            return;
+        }
+        match &self.watch {
+            Some(watch) => {
+                if watch.poll() {
+                    self.kind.reload();
+                }
+            }
+            None => {
+                let paths = self.kind.watch_paths(&self.path);
+                self.watch = Some(FileWatch::start(paths, ui.ctx().clone()));
+            }
+        }
         self.kind.show(ui);
     }
+
+    pub fn state(&self) -> TileState {
+        self.kind.state()
+    }
+
+    pub fn restore(&mut self, state: &TileState, ctx: &egui::Context) {
+        self.kind.restore(state, ctx)
+    }
+
+    /// selects the item `id`, false if the database has no item rows
+    pub fn goto(&mut self, id: u64) -> bool {
+        match &mut self.kind {
+            DatabaseKind::Entity(db) => {
+                db.goto(id);
+                true
+            }
+            DatabaseKind::Opening(db) => {
+                db.goto(id);
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
 pub enum DatabaseKind {
-    Entity(EntityDb),
-    TrieConst(TrieConstDb),
+    Entity(Box<EntityDb>),
+    Pond(Box<PondDb>),
+    Snake(SnakeDb),
+    TrieConst(Box<TrieConstDb>),
+    Trie(Box<TrieDb>),
+    Custom(Box<dyn CustomDb>),
+    Raw(RawDb),
+    Report(ReportDb),
+    Opening(Box<OpeningDb>),
 }
 
 impl DatabaseKind {
@@ -91,31 +223,117 @@ impl DatabaseKind {
             return Err(DbError::InvalidDbHead)?;
         }
         if db_head.magic.is_custom() {
-            return Err(DbError::InvalidDbHead)?;
+            return Ok(match custom::find(&db_head.magic) {
+                Some(decoder) => Self::Custom(decoder(path)?),
+                None => Self::Raw(<RawDb as Database>::init(path)?),
+            });
         }
 
         Ok(match db_head.magic.db() {
             ShahMagicDb::Entity => {
-                Self::Entity(<EntityDb as Database>::init(path)?)
+                if SnakeDb::pair(&path).is_some() {
+                    Self::Snake(<SnakeDb as Database>::init(path)?)
+                } else if PondDb::trio(&path).is_some() {
+                    Self::Pond(Box::new(<PondDb as Database>::init(path)?))
+                } else {
+                    Self::Entity(Box::new(<EntityDb as Database>::init(path)?))
+                }
+            }
+            ShahMagicDb::Snake => {
+                Self::Snake(<SnakeDb as Database>::init(path)?)
             }
-            ShahMagicDb::TrieConst => {
-                Self::TrieConst(<TrieConstDb as Database>::init(path)?)
+            ShahMagicDb::TrieConst => Self::TrieConst(Box::new(
+                <TrieConstDb as Database>::init(path)?,
+            )),
+            ShahMagicDb::Trie => {
+                Self::Trie(Box::new(<TrieDb as Database>::init(path)?))
             }
-            _ => return Err(DbError::InvalidDbHead)?,
+            _ => Self::Raw(<RawDb as Database>::init(path)?),
         })
     }
 
     pub fn title(&self) -> String {
         match self {
-            Self::Entity(db) => Database::title(db),
-            Self::TrieConst(db) => Database::title(db),
+            Self::Entity(db) => Database::title(db.as_ref()),
+            Self::Pond(db) => Database::title(db.as_ref()),
+            Self::Snake(db) => Database::title(db),
+            Self::TrieConst(db) => Database::title(db.as_ref()),
+            Self::Trie(db) => Database::title(db.as_ref()),
+            Self::Custom(db) => db.title(),
+            Self::Raw(db) => Database::title(db),
+            Self::Report(db) => db.title(),
+            Self::Opening(db) => db.title(),
         }
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui) {
         match self {
-            Self::Entity(db) => Database::show(db, ui),
-            Self::TrieConst(db) => Database::show(db, ui),
+            Self::Entity(db) => Database::show(db.as_mut(), ui),
+            Self::Pond(db) => Database::show(db.as_mut(), ui),
+            Self::Snake(db) => Database::show(db, ui),
+            Self::TrieConst(db) => Database::show(db.as_mut(), ui),
+            Self::Trie(db) => Database::show(db.as_mut(), ui),
+            Self::Custom(db) => db.show(ui),
+            Self::Raw(db) => Database::show(db, ui),
+            Self::Report(db) => db.show(ui),
+            Self::Opening(db) => db.show(ui),
+        }
+    }
+
+    pub fn reload(&mut self) {
+        match self {
+            Self::Entity(db) => Database::reload(db.as_mut()),
+            Self::Pond(db) => Database::reload(db.as_mut()),
+            Self::Snake(db) => Database::reload(db),
+            Self::TrieConst(db) => Database::reload(db.as_mut()),
+            Self::Trie(db) => Database::reload(db.as_mut()),
+            Self::Custom(db) => db.reload(),
+            Self::Raw(db) => Database::reload(db),
+            Self::Report(db) => db.reload(),
+            Self::Opening(_) => {}
+        }
+    }
+
+    pub fn state(&self) -> TileState {
+        match self {
+            Self::Entity(db) => Database::state(db.as_ref()),
+            Self::Pond(db) => Database::state(db.as_ref()),
+            Self::Snake(db) => Database::state(db),
+            Self::TrieConst(db) => Database::state(db.as_ref()),
+            Self::Trie(db) => Database::state(db.as_ref()),
+            Self::Custom(db) => db.state(),
+            Self::Raw(db) => Database::state(db),
+            Self::Report(_) => TileState::default(),
+            Self::Opening(db) => db.state(),
+        }
+    }
+
+    pub fn restore(&mut self, state: &TileState, ctx: &egui::Context) {
+        match self {
+            Self::Entity(db) => Database::restore(db.as_mut(), state, ctx),
+            Self::Pond(db) => Database::restore(db.as_mut(), state, ctx),
+            Self::Snake(db) => Database::restore(db, state, ctx),
+            Self::TrieConst(db) => Database::restore(db.as_mut(), state, ctx),
+            Self::Trie(db) => Database::restore(db.as_mut(), state, ctx),
+            Self::Custom(db) => db.restore(state, ctx),
+            Self::Raw(db) => Database::restore(db, state, ctx),
+            Self::Report(_) => {}
+            Self::Opening(db) => db.restore(state),
+        }
+    }
+
+    /// every file the database reads from
+    fn watch_paths(&self, path: &std::path::Path) -> Vec<PathBuf> {
+        match self {
+            Self::Snake(_) => match SnakeDb::pair(path) {
+                Some((index, snake)) => vec![index, snake],
+                None => vec![path.to_path_buf()],
+            },
+            Self::Pond(_) => match PondDb::trio(path) {
+                Some(p) => vec![p.item, p.pond, p.origin],
+                None => vec![path.to_path_buf()],
+            },
+            _ => vec![path.to_path_buf()],
         }
     }
 }
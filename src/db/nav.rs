@@ -0,0 +1,167 @@
+pub use super::pond::link::Link;
+use shah::db::entity::{EntityHead, ENTITY_META};
+use shah::models::{Binary, Gene, Schema, SchemaModel, ShahMagicDb};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// a navigation asked for by a database pane, handled by the app
+#[derive(Clone)]
+pub enum Request {
+    /// find the databases a gene may point to
+    Pick(Gene),
+    /// show the item with this id of the database at the path
+    Open(PathBuf, u64),
+    /// show the integrity report of the entity file at the path
+    Check(PathBuf),
+}
+
+fn requests_id() -> egui::Id {
+    egui::Id::new("nav-requests")
+}
+
+fn known_id() -> egui::Id {
+    egui::Id::new("nav-known")
+}
+
+/// queues a request for the app, which takes them after the tiles are
+/// drawn
+pub fn push(ctx: &egui::Context, req: Request) {
+    ctx.data_mut(|d| {
+        d.get_temp_mut_or_default::<Vec<Request>>(requests_id()).push(req)
+    });
+}
+
+pub fn take(ctx: &egui::Context) -> Vec<Request> {
+    ctx.data_mut(|d| d.remove_temp::<Vec<Request>>(requests_id()))
+        .unwrap_or_default()
+}
+
+/// (scope, path) of every database file found under the db paths
+pub fn set_known(ctx: &egui::Context, paths: Vec<(String, PathBuf)>) {
+    ctx.data_mut(|d| d.insert_temp(known_id(), Arc::new(paths)));
+}
+
+/// every known database file of a scope
+pub fn scope_paths(ctx: &egui::Context, scope: &str) -> Vec<PathBuf> {
+    let known =
+        ctx.data(|d| d.get_temp::<Arc<Vec<(String, PathBuf)>>>(known_id()));
+    let Some(known) = known else { return Vec::new() };
+    known.iter().filter(|(s, _)| s == scope).map(|(_, p)| p.clone()).collect()
+}
+
+/// (name, offset) of every gene inside a value of `schema`. names are
+/// dotted like the entity fields, array items are `name[i]`
+pub fn gene_fields(
+    schema: &Schema, name: &str, offset: usize, out: &mut Vec<(String, usize)>,
+) {
+    let join = |n: &str| {
+        if name.is_empty() {
+            n.to_string()
+        } else {
+            format!("{name}.{n}")
+        }
+    };
+    match schema {
+        Schema::Gene => out.push((name.to_string(), offset)),
+        Schema::Model(m) => {
+            let mut pos = offset;
+            for (n, s) in m.fields.iter() {
+                gene_fields(s, &join(n), pos, out);
+                pos += s.size();
+            }
+        }
+        Schema::Tuple(list) => {
+            let mut pos = offset;
+            for (i, s) in list.iter().enumerate() {
+                gene_fields(s, &join(&i.to_string()), pos, out);
+                pos += s.size();
+            }
+        }
+        Schema::Array { is_str: false, length, kind } => {
+            for i in 0..*length as usize {
+                let n = format!("{name}[{i}]");
+                gene_fields(kind, &n, offset + i * kind.size(), out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// a clickable gene. clicking it asks the app where the gene points to
+pub fn gene_link(gene: &Gene, ui: &mut egui::Ui) -> egui::Response {
+    let text = format!(
+        "Gene({}, {}, {:?}, {})",
+        gene.id, gene.iter, gene.pepper, gene.server
+    );
+    let res = ui.link(text).on_hover_text("find the item of this gene");
+    if res.clicked() {
+        push(ui.ctx(), Request::Pick(*gene));
+    }
+    res
+}
+
+/// offset of the item's own `gene` field
+pub fn gene_offset(model: &SchemaModel) -> Option<usize> {
+    let mut pos = 0;
+    for (name, s) in model.fields.iter() {
+        if name == "gene" && matches!(s, Schema::Gene) {
+            return Some(pos);
+        }
+        pos += s.size();
+    }
+    None
+}
+
+/// opens an entity file read only with its head and model. None if the
+/// file is anything else or its items are too small for the model
+pub fn open_entity(path: &Path) -> Option<(File, EntityHead, SchemaModel)> {
+    read_entity(path).filter(|(_, head, model)| head.item_size >= model.size)
+}
+
+/// like [`open_entity`] but keeps a head whose item size does not fit the
+/// model, the items can not be sliced into fields then
+pub fn read_entity(path: &Path) -> Option<(File, EntityHead, SchemaModel)> {
+    let file = OpenOptions::new().read(true).open(path).ok()?;
+    let mut head = EntityHead::default();
+    file.read_exact_at(head.as_binary_mut(), 0).ok()?;
+    let magic = &head.db_head.magic;
+    if !magic.is_valid() || !matches!(magic.db(), ShahMagicDb::Entity) {
+        return None;
+    }
+    if head.item_size == 0 {
+        return None;
+    }
+    match Schema::decode(&head.schema) {
+        Ok(Schema::Model(model)) => Some((file, head, model)),
+        _ => None,
+    }
+}
+
+/// checks `gene` against the item with the same id in the entity database
+/// at `path`. None if the file is not an entity with a `gene` field
+pub fn resolve(path: &Path, gene: &Gene) -> Option<Link> {
+    let (mut file, head, model) = open_entity(path)?;
+    let offset = gene_offset(&model)? as u64;
+
+    let len = file.seek(SeekFrom::End(0)).ok()?;
+    let total = len.saturating_sub(ENTITY_META) / head.item_size;
+    if gene.id.0 >= total {
+        return Some(Link::Dangling);
+    }
+
+    let mut target = Gene::default();
+    let at = ENTITY_META + gene.id.0 * head.item_size + offset;
+    file.read_exact_at(target.as_binary_mut(), at).ok()?;
+    Some(if target.is_none() {
+        Link::Broken("target item is empty")
+    } else if target.id != gene.id {
+        Link::Broken("target gene id does not match its position")
+    } else if target.iter != gene.iter || target.pepper != gene.pepper {
+        Link::Stale
+    } else {
+        Link::Valid
+    })
+}
@@ -0,0 +1,115 @@
+use super::worker::Worker;
+use super::{DatabaseKind, TileState};
+use crate::utils::db_name;
+use std::path::PathBuf;
+
+type Opened = std::result::Result<DatabaseKind, String>;
+
+/// a database that is read on a background thread. the view and the item
+/// asked for in the meantime are applied once it is open
+pub struct OpeningDb {
+    path: PathBuf,
+    worker: Worker<PathBuf, Opened>,
+    error: Option<String>,
+    state: Option<TileState>,
+    goto: Option<u64>,
+}
+
+impl OpeningDb {
+    pub fn start(path: PathBuf, ctx: &egui::Context) -> Self {
+        let mut worker = Worker::start(|path: PathBuf| {
+            DatabaseKind::new(path.clone())
+                .map_err(|e| format!("could not open {path:?}: {e:?}"))
+        });
+        worker.request(path.clone(), ctx);
+        Self { path, worker, error: None, state: None, goto: None }
+    }
+
+    pub fn title(&self) -> String {
+        let (_, db, kind) = db_name(&self.path);
+        format!("{db}/{kind}")
+    }
+
+    /// the database once it is open, with the pending view applied
+    pub fn poll(&mut self, ctx: &egui::Context) -> Option<DatabaseKind> {
+        let mut kind = match self.worker.poll()? {
+            Ok(kind) => kind,
+            Err(e) => {
+                log::error!("{e}");
+                self.error = Some(e);
+                return None;
+            }
+        };
+        if let Some(state) = self.state.take() {
+            kind.restore(&state, ctx);
+        }
+        if let (Some(id), DatabaseKind::Entity(db)) = (self.goto, &mut kind) {
+            db.goto(id);
+        }
+        Some(kind)
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if self.worker.busy() {
+                ui.spinner();
+            }
+            ui.label(format!("opening: {}", self.path.display()));
+        });
+        if let Some(e) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+    }
+
+    pub fn state(&self) -> TileState {
+        self.state.clone().unwrap_or_default()
+    }
+
+    pub fn restore(&mut self, state: &TileState) {
+        self.state = Some(state.clone());
+    }
+
+    pub fn goto(&mut self, id: u64) {
+        self.goto = Some(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn wait(db: &mut OpeningDb, ctx: &egui::Context) -> Option<DatabaseKind> {
+        while db.worker.busy() {
+            if let Some(kind) = db.poll(ctx) {
+                return Some(kind);
+            }
+            std::thread::yield_now();
+        }
+        db.poll(ctx)
+    }
+
+    #[test]
+    fn pending_view() {
+        let ctx = egui::Context::default();
+        let mut db = OpeningDb::start(fixtures::get().item.clone(), &ctx);
+        let state = TileState { skip: Some(2), ..Default::default() };
+        db.restore(&state);
+        assert_eq!(db.state(), state);
+        let kind = wait(&mut db, &ctx).unwrap();
+        assert!(matches!(kind, DatabaseKind::Entity(_)));
+        assert_eq!(kind.state().skip, Some(2));
+    }
+
+    #[test]
+    fn not_a_database() {
+        let ctx = egui::Context::default();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.shah");
+        std::fs::write(&path, b"").unwrap();
+        let mut db = OpeningDb::start(path, &ctx);
+        assert!(wait(&mut db, &ctx).is_none());
+        let e = db.error.unwrap();
+        assert!(e.starts_with("could not open"), "{e}");
+    }
+}
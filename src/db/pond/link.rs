@@ -0,0 +1,117 @@
+use crate::Result;
+use shah::db::entity::{Entity, EntityHead, ENTITY_META};
+use shah::models::{Binary, Gene};
+use shah::DbError;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::marker::PhantomData;
+use std::{fs::OpenOptions, os::unix::fs::FileExt, path::Path};
+
+/// the state of a Gene pointer after resolving it against its target file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Link {
+    Empty,
+    Valid,
+    /// the id is past the end of the target file
+    Dangling,
+    /// the target exists but its gene has a different iter or pepper
+    Stale,
+    /// the target exists but does not agree with the link
+    Broken(&'static str),
+}
+
+impl Link {
+    pub fn followable(&self) -> bool {
+        matches!(self, Self::Valid | Self::Stale | Self::Broken(_))
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Empty => "empty link",
+            Self::Valid => "valid link",
+            Self::Dangling => "dangling link: id is out of bound",
+            Self::Stale => "stale link: target gene does not match",
+            Self::Broken(reason) => reason,
+        }
+    }
+
+    pub fn color(&self, visuals: &egui::Visuals) -> Option<egui::Color32> {
+        match self {
+            Self::Empty | Self::Valid => None,
+            Self::Dangling => Some(visuals.error_fg_color),
+            Self::Stale | Self::Broken(_) => Some(visuals.warn_fg_color),
+        }
+    }
+}
+
+pub fn link_button(
+    ui: &mut egui::Ui, name: &str, gene: &Gene, link: Link,
+) -> egui::Response {
+    let mut text = egui::RichText::new(format!(
+        "{name}({}, {}, {:?}, {})",
+        gene.id, gene.iter, gene.pepper, gene.server
+    ));
+    if let Some(color) = link.color(ui.visuals()) {
+        text = text.color(color);
+    }
+
+    ui.add_enabled(link.followable(), egui::Button::new(text))
+        .on_hover_text(link.describe())
+        .on_disabled_hover_text(link.describe())
+}
+
+/// a read only view over one of the entity files of a pond
+pub struct EntityFile<T> {
+    file: File,
+    pub total: u64,
+    _item: PhantomData<T>,
+}
+
+impl<T: Entity> EntityFile<T> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new().read(true).open(path)?;
+        let mut head = EntityHead::default();
+        file.read_exact_at(head.as_binary_mut(), 0)?;
+        if head.item_size != T::N {
+            return Err(DbError::InvalidDbSchema)?;
+        }
+
+        let mut ef = Self { file, total: 0, _item: PhantomData };
+        ef.update_total()?;
+        Ok(ef)
+    }
+
+    pub fn update_total(&mut self) -> Result<()> {
+        let pos = self.file.seek(SeekFrom::End(0))?;
+        self.total = pos.saturating_sub(ENTITY_META) / T::N;
+        Ok(())
+    }
+
+    pub fn get(&self, id: u64) -> Option<T> {
+        if id >= self.total {
+            return None;
+        }
+        let mut item = T::default();
+        let pos = ENTITY_META + id * T::N;
+        self.file.read_exact_at(item.as_binary_mut(), pos).ok()?;
+        Some(item)
+    }
+
+    pub fn list(&self, skip: u64, show: u64) -> Vec<(u64, T)> {
+        let max = (skip + show).min(self.total);
+        (skip..max).filter_map(|id| Some((id, self.get(id)?))).collect()
+    }
+
+    pub fn resolve(&self, gene: &Gene) -> (Link, Option<T>) {
+        if gene.is_none() {
+            return (Link::Empty, None);
+        }
+        let Some(item) = self.get(gene.id.0) else {
+            return (Link::Dangling, None);
+        };
+        if item.gene() != gene || !item.is_alive() {
+            return (Link::Stale, Some(item));
+        }
+        (Link::Valid, Some(item))
+    }
+}
@@ -0,0 +1,648 @@
+pub mod link;
+
+use super::entity::Field;
+use super::worker::Worker;
+use super::{Database, TileState, Value};
+use crate::error::ShahVisualError;
+use crate::utils::{self, db_name};
+use crate::Result;
+use egui_extras as ee;
+use link::{link_button, EntityFile, Link};
+use shah::db::entity::{Entity, EntityHead, ENTITY_META};
+use shah::db::pond::{ShahOrigin, ShahPond};
+use shah::models::{Binary, Gene, Schema, SchemaModel};
+use shah::{DbError, PAGE_SIZE};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::ops::{DerefMut, Range};
+use std::path::Path;
+use std::{fs::OpenOptions, os::unix::fs::FileExt, path::PathBuf};
+
+const INDEX_DIR: &str = "index";
+const ORIGIN_DIR: &str = "origin";
+/// upper bound for walking a pond chain, in case the links form a cycle
+const CHAIN_MAX: usize = 10_000;
+
+pub struct PondPaths {
+    pub item: PathBuf,
+    pub pond: PathBuf,
+    pub origin: PathBuf,
+}
+
+/// the raw item file of a pond, decoded with its own schema
+struct ItemFile {
+    file: File,
+    size: u64,
+    total: u64,
+    schema: SchemaModel,
+    fields: Vec<Field>,
+    /// byte range of the `pond: Gene` field of each item, if any
+    pond_range: Option<Range<usize>>,
+}
+
+enum Nav {
+    Origin(u64),
+    Pond(u64),
+}
+
+struct ChainPond {
+    id: u64,
+    gene: Gene,
+    link: Link,
+}
+
+/// the ponds of an origin with its head and tail links
+type Chain = (Vec<ChainPond>, [Link; 2]);
+
+/// walks the pond linked list of `origin` from head to tail
+fn walk_chain(ponds: &EntityFile<ShahPond>, origin: &ShahOrigin) -> Chain {
+    let mut chain = Vec::new();
+    let mut seen = HashSet::<u64>::new();
+    let mut past = Gene::NONE;
+    let mut gene = origin.head;
+    while gene.is_some() && chain.len() < CHAIN_MAX {
+        let (mut link, pond) = ponds.resolve(&gene);
+        if !seen.insert(gene.id.0) {
+            link = Link::Broken("cycle in the pond chain");
+            chain.push(ChainPond { id: gene.id.0, gene, link });
+            break;
+        }
+        if let (Link::Valid, Some(p)) = (link, &pond) {
+            if p.origin != origin.gene {
+                link = Link::Broken("pond belongs to another origin");
+            } else if p.past != past {
+                link = Link::Broken("past link does not match the chain");
+            }
+        }
+        chain.push(ChainPond { id: gene.id.0, gene, link });
+
+        let Some(p) = pond else { break };
+        past = gene;
+        gene = p.next;
+    }
+
+    let head = ponds.resolve(&origin.head).0;
+    let tail = match ponds.resolve(&origin.tail).0 {
+        Link::Valid if origin.tail != past => {
+            Link::Broken("tail is not the last pond of the chain")
+        }
+        link => link,
+    };
+    (chain, [head, tail])
+}
+
+pub struct PondDb {
+    name: String,
+    prefix: String,
+    revision: u16,
+    item: ItemFile,
+    pond: EntityFile<ShahPond>,
+    origin: EntityFile<ShahOrigin>,
+    origin_skip: Value<u64>,
+    origin_show: Value<u64>,
+    origins: Vec<(u64, ShahOrigin)>,
+    active_origin: Option<(u64, ShahOrigin)>,
+    /// head and tail links of the active origin
+    origin_links: [Link; 2],
+    chain: Vec<ChainPond>,
+    /// walks the chain of an origin, it can be thousands of reads
+    chain_worker: Worker<ShahOrigin, Chain>,
+    chain_stale: bool,
+    active_pond: Option<(u64, ShahPond)>,
+    /// next, past and origin links of the active pond
+    pond_links: [Link; 3],
+    items: Vec<(u64, Vec<u8>)>,
+}
+
+impl Database for PondDb {
+    fn init(path: PathBuf) -> Result<Self> {
+        Self::init(path)
+    }
+
+    fn title(&self) -> String {
+        if self.prefix.is_empty() {
+            format!("{}.{}.pond", self.name, self.revision)
+        } else {
+            format!("{}/{}.{}.pond", self.prefix, self.name, self.revision)
+        }
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        self.show(ui);
+    }
+
+    fn reload(&mut self) {
+        let totals = [
+            self.item.update_total(),
+            self.pond.update_total(),
+            self.origin.update_total(),
+        ];
+        for res in totals {
+            if let Err(e) = res {
+                log::error!("could not reload pond {}: {e:?}", self.name);
+            }
+        }
+        self.update_origins();
+        if let Some((id, _)) = self.active_origin {
+            self.select_origin(id);
+        }
+        if let Some((id, _)) = self.active_pond {
+            self.select_pond(id);
+        }
+    }
+
+    fn state(&self) -> TileState {
+        TileState {
+            skip: Some(self.origin_skip.main()),
+            show: Some(self.origin_show.main()),
+            ..Default::default()
+        }
+    }
+
+    fn restore(&mut self, state: &TileState, _: &egui::Context) {
+        if let Some(skip) = state.skip {
+            *self.origin_skip = skip;
+        }
+        if let Some(show) = state.show {
+            *self.origin_show = show;
+        }
+    }
+}
+
+/// finds `{name}.{revision}.shah` with the highest revision inside of dir
+fn latest_revision(dir: &Path, name: &str) -> Option<PathBuf> {
+    let it = dir.read_dir().ok()?.filter_map(|e| {
+        let path = e.ok()?.path();
+        let file = path.file_name()?.to_str()?;
+        let rev = file.strip_prefix(name)?.strip_prefix('.')?;
+        let rev = rev.strip_suffix(".shah")?.parse::<u16>().ok()?;
+        Some((rev, path))
+    });
+    it.max_by_key(|(rev, _)| *rev).map(|(_, p)| p)
+}
+
+impl PondDb {
+    /// returns the item, pond index and origin paths for a path
+    /// pointing at any one of the three files of a pond database
+    pub fn trio(path: &Path) -> Option<PondPaths> {
+        let parent = path.parent()?;
+        let role = parent.file_name()?.to_str()?;
+        let dir = match role {
+            INDEX_DIR | ORIGIN_DIR => parent.parent()?,
+            _ => parent,
+        };
+        let name = dir.file_name()?.to_str()?;
+
+        // most entities are not ponds, so look for the two directories
+        // before listing any of them for their latest revision
+        let index_dir = dir.join(INDEX_DIR);
+        let origin_dir = dir.join(ORIGIN_DIR);
+        if !index_dir.is_dir() || !origin_dir.is_dir() {
+            return None;
+        }
+
+        let pick = |r: &str, dir: &Path, name: &str| {
+            if r == role {
+                Some(path.to_path_buf())
+            } else {
+                latest_revision(dir, name)
+            }
+        };
+        let item = if role == INDEX_DIR || role == ORIGIN_DIR {
+            latest_revision(dir, name)?
+        } else {
+            path.to_path_buf()
+        };
+
+        Some(PondPaths {
+            item,
+            pond: pick(INDEX_DIR, &index_dir, INDEX_DIR)?,
+            origin: pick(ORIGIN_DIR, &origin_dir, ORIGIN_DIR)?,
+        })
+    }
+
+    pub fn init(path: PathBuf) -> Result<Self> {
+        let Some(paths) = Self::trio(&path) else {
+            return Err(ShahVisualError::MissingFile(path));
+        };
+
+        let file = OpenOptions::new().read(true).open(&paths.item)?;
+        let mut head = EntityHead::default();
+        file.read_exact_at(head.as_binary_mut(), 0)?;
+
+        let schema = match Schema::decode(&head.schema)? {
+            Schema::Model(m) => m,
+            _ => return Err(DbError::InvalidDbSchema)?,
+        };
+
+        let mut offset = 0usize;
+        let mut pond_range = None;
+        for (name, kind) in schema.fields.iter() {
+            let s = kind.size();
+            if name == "pond" && matches!(kind, Schema::Gene) {
+                pond_range = Some(offset..offset + s);
+            }
+            offset += s;
+        }
+
+        let mut item = ItemFile {
+            file,
+            size: head.item_size,
+            total: 0,
+            fields: Field::from_model(&schema),
+            schema,
+            pond_range,
+        };
+        item.update_total()?;
+
+        let (_, prefix, _) = db_name(&paths.item);
+        let name = head.db_head.name().to_string();
+        let prefix = if name == prefix { "" } else { prefix };
+
+        let mut ponds = EntityFile::<ShahPond>::open(&paths.pond)?;
+        let chain_worker = Worker::start(move |origin: ShahOrigin| {
+            if let Err(e) = ponds.update_total() {
+                log::error!("could not read the pond total: {e:?}");
+            }
+            walk_chain(&ponds, &origin)
+        });
+
+        let mut db = Self {
+            name,
+            prefix: prefix.to_string(),
+            revision: head.db_head.revision,
+            item,
+            pond: EntityFile::open(&paths.pond)?,
+            origin: EntityFile::open(&paths.origin)?,
+            origin_skip: Value::new(0),
+            origin_show: Value::new(10),
+            origins: Vec::with_capacity(10),
+            active_origin: None,
+            origin_links: [Link::Empty; 2],
+            chain: Vec::new(),
+            chain_worker,
+            chain_stale: false,
+            active_pond: None,
+            pond_links: [Link::Empty; 3],
+            items: Vec::with_capacity(PAGE_SIZE),
+        };
+
+        db.update_origins();
+
+        Ok(db)
+    }
+
+    fn update_origins(&mut self) {
+        let skip = self.origin_skip.main();
+        self.origins = self.origin.list(skip, self.origin_show.main());
+    }
+
+    fn select_origin(&mut self, id: u64) {
+        self.active_origin = self.origin.get(id).map(|o| (id, o));
+        self.update_chain();
+    }
+
+    fn select_pond(&mut self, id: u64) {
+        self.active_pond = self.pond.get(id).map(|p| (id, p));
+        self.update_pond();
+    }
+
+    /// queues a walk of the chain of the active origin
+    fn update_chain(&mut self) {
+        self.chain.clear();
+        self.origin_links = [Link::Empty; 2];
+        self.chain_stale = self.active_origin.is_some();
+    }
+
+    fn update_pond(&mut self) {
+        self.items.clear();
+        self.pond_links = [Link::Empty; 3];
+        let Some((_, pond)) = &self.active_pond else { return };
+
+        self.pond_links[0] = match self.pond.resolve(&pond.next) {
+            (Link::Valid, Some(next)) if next.past != pond.gene => {
+                Link::Broken("next pond does not point back to this pond")
+            }
+            (link, _) => link,
+        };
+        self.pond_links[1] = match self.pond.resolve(&pond.past) {
+            (Link::Valid, Some(past)) if past.next != pond.gene => {
+                Link::Broken("past pond does not point to this pond")
+            }
+            (link, _) => link,
+        };
+        self.pond_links[2] = self.origin.resolve(&pond.origin).0;
+
+        let stack = pond.stack.0;
+        if stack == 0 {
+            return;
+        }
+        for id in stack..(stack + PAGE_SIZE as u64).min(self.item.total) {
+            if let Some(buf) = self.item.get(id) {
+                self.items.push((id, buf));
+            }
+        }
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        if self.origin_skip.changed() || self.origin_show.changed() {
+            self.update_origins();
+        }
+        if let Some((chain, links)) = self.chain_worker.poll() {
+            if self.active_origin.is_some() {
+                self.chain = chain;
+                self.origin_links = links;
+            }
+        }
+        if std::mem::take(&mut self.chain_stale) {
+            if let Some((_, origin)) = &self.active_origin {
+                self.chain_worker.request(*origin, ui.ctx());
+            }
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label(format!("db: {}.{}", self.name, self.revision));
+            ui.label(format!(
+                "model: <{} {}/>",
+                self.item.schema.name, self.item.size
+            ));
+            ui.label(format!("origins: {}", self.origin.total));
+            ui.label(format!("ponds: {}", self.pond.total));
+            ui.label(format!("items: {}", self.item.total));
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::Slider::new(
+                    self.origin_skip.deref_mut(),
+                    0..=self.origin.total,
+                )
+                .text("skip"),
+            );
+            ui.separator();
+            ui.add(
+                egui::Slider::new(
+                    self.origin_show.deref_mut(),
+                    0..=self.origin.total,
+                )
+                .text("show"),
+            );
+        });
+        ui.separator();
+
+        let mut nav = None;
+        egui::Frame::new()
+            .stroke(ui.style().visuals.window_stroke)
+            .show(ui, |ui| self.show_origins(ui, &mut nav));
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            self.show_active_origin(ui, &mut nav);
+            self.show_active_pond(ui, &mut nav);
+        });
+
+        match nav {
+            Some(Nav::Origin(id)) => self.select_origin(id),
+            Some(Nav::Pond(id)) => self.select_pond(id),
+            None => {}
+        }
+    }
+
+    fn show_origins(&mut self, ui: &mut egui::Ui, nav: &mut Option<Nav>) {
+        let active = self.active_origin.as_ref().map(|(id, _)| *id);
+        ee::TableBuilder::new(ui)
+            .id_salt("pond-origins")
+            .column(ee::Column::auto().resizable(true).at_least(50.0))
+            .columns(ee::Column::remainder().resizable(true).clip(true), 5)
+            .striped(true)
+            .resizable(true)
+            .max_scroll_height(200.0)
+            .sense(egui::Sense::click())
+            .header(25.0, |mut header| {
+                for h in ["id", "gene", "head", "ponds", "items", "alive"] {
+                    header.col(|ui| {
+                        ui.heading(h);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, self.origins.len(), |mut row| {
+                    let (id, o) = &self.origins[row.index()];
+                    row.set_selected(active == Some(*id));
+                    row.col(|ui| {
+                        ui.label(id.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(gene_str(&o.gene));
+                    });
+                    row.col(|ui| {
+                        ui.label(gene_str(&o.head));
+                    });
+                    row.col(|ui| {
+                        ui.label(o.pond_count.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(o.item_count.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(o.is_alive().to_string());
+                    });
+                    if row.response().clicked() {
+                        *nav = Some(Nav::Origin(*id));
+                    }
+                });
+            });
+    }
+
+    fn show_active_origin(&self, ui: &mut egui::Ui, nav: &mut Option<Nav>) {
+        let Some((id, origin)) = &self.active_origin else { return };
+
+        egui::Frame::default()
+            .fill(ui.style().visuals.window_fill)
+            .stroke(utils::stroke(origin.is_alive(), false, ui.visuals()))
+            .inner_margin(8.0)
+            .outer_margin(8.0)
+            .corner_radius(5.0)
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(format!("origin: {id}"));
+                    utils::gene(&origin.gene, "Gene", ui);
+                    let [head, tail] = self.origin_links;
+                    if link_button(ui, "Head", &origin.head, head).clicked() {
+                        *nav = Some(Nav::Pond(origin.head.id.0));
+                    }
+                    if link_button(ui, "Tail", &origin.tail, tail).clicked() {
+                        *nav = Some(Nav::Pond(origin.tail.id.0));
+                    }
+                    ui.label(format!("items: {}", origin.item_count));
+                    ui.add(utils::ColoredBool::new(
+                        "is alive",
+                        origin.is_alive(),
+                    ));
+                });
+                ui.separator();
+
+                if self.chain_worker.busy() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("walking the chain");
+                    });
+                    return;
+                }
+                let ponds = format!(
+                    "chain: {} / pond count: {}",
+                    self.chain.len(),
+                    origin.pond_count
+                );
+                if self.chain.len() as u64 == origin.pond_count {
+                    ui.label(ponds);
+                } else {
+                    ui.colored_label(ui.visuals().warn_fg_color, ponds)
+                        .on_hover_text("pond count does not match the chain");
+                }
+
+                let active = self.active_pond.as_ref().map(|(id, _)| *id);
+                ui.horizontal_wrapped(|ui| {
+                    for (i, cp) in self.chain.iter().enumerate() {
+                        if i != 0 {
+                            ui.label("→");
+                        }
+                        let mut text = egui::RichText::new(cp.id.to_string());
+                        if let Some(c) = cp.link.color(ui.visuals()) {
+                            text = text.color(c);
+                        }
+                        let btn = egui::Button::new(text)
+                            .selected(active == Some(cp.id));
+                        let res = ui.add_enabled(cp.link.followable(), btn);
+                        let res = res.on_hover_text(format!(
+                            "{}\n{}",
+                            gene_str(&cp.gene),
+                            cp.link.describe()
+                        ));
+                        if res.clicked() {
+                            *nav = Some(Nav::Pond(cp.id));
+                        }
+                    }
+                });
+            });
+    }
+
+    fn show_active_pond(&self, ui: &mut egui::Ui, nav: &mut Option<Nav>) {
+        let Some((id, pond)) = &self.active_pond else { return };
+
+        egui::Frame::default()
+            .fill(ui.style().visuals.window_fill)
+            .stroke(utils::stroke(pond.is_alive(), false, ui.visuals()))
+            .inner_margin(8.0)
+            .outer_margin(8.0)
+            .corner_radius(5.0)
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(format!("pond: {id}"));
+                    utils::gene(&pond.gene, "Gene", ui);
+                    let [next, past, origin] = self.pond_links;
+                    if link_button(ui, "Origin", &pond.origin, origin).clicked()
+                    {
+                        *nav = Some(Nav::Origin(pond.origin.id.0));
+                    }
+                    if link_button(ui, "Past", &pond.past, past).clicked() {
+                        *nav = Some(Nav::Pond(pond.past.id.0));
+                    }
+                    if link_button(ui, "Next", &pond.next, next).clicked() {
+                        *nav = Some(Nav::Pond(pond.next.id.0));
+                    }
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(format!("stack: {}", pond.stack));
+                    ui.label(format!("alive: {}", pond.alive));
+                    ui.label(format!("empty: {}", pond.empty));
+                    ui.add(utils::ColoredBool::new(
+                        "is alive",
+                        pond.is_alive(),
+                    ));
+                });
+                ui.separator();
+                self.show_items(ui, &pond.gene);
+            });
+    }
+
+    fn show_items(&self, ui: &mut egui::Ui, pond: &Gene) {
+        let fields = self.item.fields.iter().filter(|f| f.show_array);
+        ee::TableBuilder::new(ui)
+            .id_salt("pond-items")
+            .column(ee::Column::auto().resizable(true).at_least(50.0))
+            .columns(
+                ee::Column::remainder()
+                    .resizable(true)
+                    .clip(true)
+                    .at_least(40.0),
+                fields.clone().count(),
+            )
+            .striped(true)
+            .resizable(true)
+            .vscroll(false)
+            .header(25.0, |mut header| {
+                header.col(|ui| {
+                    ui.heading("id");
+                });
+                for f in fields.clone() {
+                    header.col(|ui| {
+                        ui.heading(&f.name);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, self.items.len(), |mut row| {
+                    let (id, item) = &self.items[row.index()];
+                    row.col(|ui| {
+                        let owner =
+                            self.item.pond_range.clone().map(|r| {
+                                Gene::from_binary(&item[r]).to_owned()
+                            });
+                        match owner {
+                            Some(g) if g.is_some() && g != *pond => {
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    id.to_string(),
+                                )
+                                .on_hover_text(format!(
+                                    "item points at another pond: {}",
+                                    gene_str(&g)
+                                ));
+                            }
+                            _ => {
+                                ui.label(id.to_string());
+                            }
+                        }
+                    });
+                    for f in fields.clone() {
+                        row.col(|ui| f.show(&item[f.range.clone()], ui));
+                    }
+                });
+            });
+    }
+}
+
+impl ItemFile {
+    fn update_total(&mut self) -> Result<()> {
+        if self.size == 0 {
+            return Err(DbError::InvalidDbMeta)?;
+        }
+        let pos = self.file.seek(SeekFrom::End(0))?;
+        self.total = pos.saturating_sub(ENTITY_META) / self.size;
+        Ok(())
+    }
+
+    fn get(&self, id: u64) -> Option<Vec<u8>> {
+        let mut buf = vec![0u8; self.size as usize];
+        let pos = ENTITY_META + id * self.size;
+        self.file.read_exact_at(&mut buf, pos).ok()?;
+        Some(buf)
+    }
+}
+
+fn gene_str(g: &Gene) -> String {
+    if g.is_none() {
+        return "---".to_string();
+    }
+    format!("Gene({}, {}, {:?}, {})", g.id, g.iter, g.pepper, g.server)
+}
@@ -0,0 +1,132 @@
+use super::{Database, TileState, Value};
+use crate::utils::{self, db_name};
+use crate::Result;
+use shah::models::{Binary, DbHead};
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::ops::DerefMut;
+use std::{fs::OpenOptions, os::unix::fs::FileExt, path::PathBuf};
+
+/// how many bytes of the file are read from disk at once
+const PAGE: u64 = 4096;
+
+/// fallback viewer for databases without a decoder,
+/// shows the DbHead and a hex view of the rest of the file
+pub struct RawDb {
+    file: File,
+    head: DbHead,
+    prefix: String,
+    file_len: u64,
+    offset: Value<u64>,
+    data: Vec<u8>,
+}
+
+impl Database for RawDb {
+    fn init(path: PathBuf) -> Result<Self> {
+        let mut file = OpenOptions::new().read(true).open(&path)?;
+        let mut head = DbHead::default();
+        file.read_exact_at(head.as_binary_mut(), 0)?;
+        let file_len = file.seek(SeekFrom::End(0))?;
+
+        let (_, prefix, _) = db_name(&path);
+        let prefix = if head.name() == prefix { "" } else { prefix };
+
+        let mut db = Self {
+            file,
+            head,
+            prefix: prefix.to_string(),
+            file_len,
+            offset: Value::new(DbHead::N),
+            data: Vec::with_capacity(PAGE as usize),
+        };
+        db.update_data();
+
+        Ok(db)
+    }
+
+    fn title(&self) -> String {
+        if self.prefix.is_empty() {
+            format!("{}.{}", self.head.name(), self.head.revision)
+        } else {
+            format!(
+                "{}/{}.{}",
+                self.prefix,
+                self.head.name(),
+                self.head.revision
+            )
+        }
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        if self.offset.changed() {
+            self.update_data();
+        }
+
+        let magic = &self.head.magic;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(format!(
+                "db: {}.{}",
+                self.head.name(),
+                self.head.revision
+            ));
+            ui.label(format!(
+                "magic: {} {}:{} ({:?})",
+                if magic.is_custom() { "custom" } else { "shah" },
+                magic.prefix(),
+                magic.raw_db(),
+                magic.db()
+            ));
+            let (major, minor) = self.head.shah_version;
+            ui.label(format!("shah version: {major}.{minor}"));
+            ui.label(format!("db version: {}", self.head.db_version));
+            ui.label(format!("file size: {}", self.file_len));
+        });
+        ui.add(
+            egui::Slider::new(
+                self.offset.deref_mut(),
+                0..=self.file_len.saturating_sub(1),
+            )
+            .step_by(utils::HEX_ROW as f64)
+            .text("offset"),
+        );
+        ui.separator();
+
+        let offset = self.offset.main();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (i, row) in self.data.chunks(utils::HEX_ROW).enumerate() {
+                let pos = offset + (i * utils::HEX_ROW) as u64;
+                ui.monospace(utils::hex_row(pos, row));
+            }
+        });
+    }
+
+    fn reload(&mut self) {
+        match self.file.seek(SeekFrom::End(0)) {
+            Ok(len) => self.file_len = len,
+            Err(e) => log::error!("could not reload raw db: {e}"),
+        }
+        self.update_data();
+    }
+
+    fn state(&self) -> TileState {
+        TileState { skip: Some(self.offset.main()), ..Default::default() }
+    }
+
+    fn restore(&mut self, state: &TileState, _: &egui::Context) {
+        if let Some(offset) = state.skip {
+            *self.offset = offset;
+        }
+    }
+}
+
+impl RawDb {
+    fn update_data(&mut self) {
+        let offset = self.offset.main().min(self.file_len);
+        let size = (self.file_len - offset).min(PAGE) as usize;
+        self.data.resize(size, 0);
+        if let Err(e) = self.file.read_exact_at(&mut self.data, offset) {
+            log::error!("could not read raw data at {offset}: {e}");
+            self.data.clear();
+        }
+    }
+}
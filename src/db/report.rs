@@ -0,0 +1,139 @@
+use super::nav;
+use super::worker::Worker;
+use crate::check::{self, Level, Report};
+use crate::utils::db_name;
+use std::path::PathBuf;
+
+type Checked = std::result::Result<Report, String>;
+
+/// the integrity report of an entity file. it is checked again when the
+/// file changes
+pub struct ReportDb {
+    path: PathBuf,
+    /// checks the file against the known paths of its scope
+    worker: Worker<Vec<PathBuf>, Checked>,
+    scope: String,
+    stale: bool,
+    report: Option<Checked>,
+    errors_only: bool,
+}
+
+impl ReportDb {
+    pub fn new(path: PathBuf) -> Self {
+        let (scope, _, _) = db_name(&path);
+        let scope = scope.to_string();
+        let target = path.clone();
+        let worker = Worker::start(move |known: Vec<PathBuf>| {
+            check::check_entity(&target, &known)
+        });
+        Self {
+            path,
+            worker,
+            scope,
+            stale: true,
+            report: None,
+            errors_only: false,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let (_, db, kind) = db_name(&self.path);
+        format!("check {db}/{kind}")
+    }
+
+    pub fn reload(&mut self) {
+        self.stale = true;
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if let Some(res) = self.worker.poll() {
+            self.report = Some(res);
+        }
+        if std::mem::take(&mut self.stale) {
+            let known = nav::scope_paths(ui.ctx(), &self.scope);
+            self.worker.request(known, ui.ctx());
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(format!("check: {}", self.path.display()));
+            if self.worker.busy() {
+                ui.spinner();
+            } else if ui.button("run again").clicked() {
+                self.stale = true;
+            }
+        });
+
+        let report = match &self.report {
+            Some(Ok(r)) => r,
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                return;
+            }
+            None => return,
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} items of {} bytes",
+                report.item_total, report.item_size
+            ));
+            ui.separator();
+            let errors = report.errors();
+            let warnings = report.warnings();
+            if errors == 0 && warnings == 0 {
+                ui.colored_label(egui::Color32::LIGHT_GREEN, "no issues");
+            } else {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("{errors} errors"),
+                );
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("{warnings} warnings"),
+                );
+            }
+            if report.hidden > 0 {
+                ui.label(format!("+{} not listed", report.hidden));
+            }
+            ui.separator();
+            ui.checkbox(&mut self.errors_only, "errors only");
+            if ui.button("copy report").clicked() {
+                let mut out = String::new();
+                report.write_text(&mut out);
+                ui.ctx().copy_text(out);
+            }
+        });
+        ui.separator();
+
+        let issues = report
+            .issues
+            .iter()
+            .filter(|i| !self.errors_only || i.level == Level::Error)
+            .collect::<Vec<_>>();
+        let height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical().auto_shrink(false).show_rows(
+            ui,
+            height,
+            issues.len(),
+            |ui, range| {
+                for issue in &issues[range] {
+                    ui.horizontal(|ui| {
+                        let color = match issue.level {
+                            Level::Warn => ui.visuals().warn_fg_color,
+                            Level::Error => ui.visuals().error_fg_color,
+                        };
+                        if let Some(id) = issue.id {
+                            if ui.link(format!("#{id}")).clicked() {
+                                nav::push(
+                                    ui.ctx(),
+                                    nav::Request::Open(self.path.clone(), id),
+                                );
+                            }
+                        }
+                        ui.colored_label(color, &issue.message);
+                    });
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use super::{Database, TileState, Value};
+use crate::error::ShahVisualError;
+use crate::utils::{self, db_name};
+use crate::Result;
+use egui_extras as ee;
+use shah::db::entity::{Entity, EntityHead, ENTITY_META};
+use shah::db::snake::SnakeHead;
+use shah::models::{Binary, DbHead, ShahMagicDb};
+use shah::DbError;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::ops::DerefMut;
+use std::path::Path;
+use std::{fs::OpenOptions, os::unix::fs::FileExt, path::PathBuf};
+
+const SNAKE_FILE: &str = "data.snake.shah";
+const INDEX_DIR: &str = "index";
+const INDEX_FILE: &str = "index.0.shah";
+/// how many bytes of the payload are read from disk at once
+const PAYLOAD_PAGE: u64 = 4096;
+const HEAD_COLUMNS: [&str; 7] =
+    ["id", "gene", "position", "capacity", "length", "alive", "free"];
+
+pub struct SnakeDb {
+    index: File,
+    snake: File,
+    name: String,
+    prefix: String,
+    revision: u16,
+    snake_len: u64,
+    head_total: u64,
+    head_skip: Value<u64>,
+    head_show: Value<u64>,
+    heads: Vec<(u64, SnakeHead)>,
+    active_head: Option<(u64, SnakeHead)>,
+    payload_skip: Value<u64>,
+    payload: Vec<u8>,
+    payload_error: Option<String>,
+}
+
+impl Database for SnakeDb {
+    fn init(path: PathBuf) -> Result<Self> {
+        Self::init(path)
+    }
+
+    fn title(&self) -> String {
+        if self.prefix.is_empty() {
+            format!("{}.snake", self.name)
+        } else {
+            format!("{}/{}.snake", self.prefix, self.name)
+        }
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        self.show(ui);
+    }
+
+    fn reload(&mut self) {
+        if let Err(e) = self.update() {
+            log::error!("could not reload snake {}: {e:?}", self.name);
+        }
+        let Some((id, _)) = self.active_head else { return };
+        let mut head = SnakeHead::default();
+        let pos = ENTITY_META + id * SnakeHead::N;
+        if self.index.read_exact_at(head.as_binary_mut(), pos).is_ok() {
+            self.active_head = Some((id, head));
+        }
+        self.update_payload();
+    }
+
+    fn state(&self) -> TileState {
+        TileState {
+            skip: Some(self.head_skip.main()),
+            show: Some(self.head_show.main()),
+            ..Default::default()
+        }
+    }
+
+    fn restore(&mut self, state: &TileState, _: &egui::Context) {
+        if let Some(skip) = state.skip {
+            *self.head_skip = skip;
+        }
+        if let Some(show) = state.show {
+            *self.head_show = show;
+        }
+    }
+}
+
+impl SnakeDb {
+    /// returns the (index, snake) paths for a path pointing at
+    /// either one of the two files of a snake database
+    pub fn pair(path: &Path) -> Option<(PathBuf, PathBuf)> {
+        let name = path.file_name()?.to_str()?;
+        if name == SNAKE_FILE {
+            let dir = path.parent()?;
+            let index = dir.join(INDEX_DIR).join(INDEX_FILE);
+            return Some((index, path.to_path_buf()));
+        }
+
+        let index_dir = path.parent()?;
+        if index_dir.file_name()?.to_str()? != INDEX_DIR {
+            return None;
+        }
+        let snake = index_dir.parent()?.join(SNAKE_FILE);
+        if !snake.is_file() {
+            return None;
+        }
+
+        Some((path.to_path_buf(), snake))
+    }
+
+    pub fn init(path: PathBuf) -> Result<Self> {
+        let Some((index_path, snake_path)) = Self::pair(&path) else {
+            return Err(ShahVisualError::MissingFile(path));
+        };
+        if !index_path.is_file() {
+            return Err(ShahVisualError::MissingFile(index_path));
+        }
+
+        let index = OpenOptions::new().read(true).open(&index_path)?;
+        let mut snake = OpenOptions::new().read(true).open(&snake_path)?;
+
+        let mut head = EntityHead::default();
+        index.read_exact_at(head.as_binary_mut(), 0)?;
+        if head.item_size != SnakeHead::N {
+            return Err(DbError::InvalidDbSchema)?;
+        }
+
+        let mut snake_head = DbHead::default();
+        snake.read_exact_at(snake_head.as_binary_mut(), 0)?;
+        if !snake_head.magic.is_valid()
+            || !matches!(snake_head.magic.db(), ShahMagicDb::Snake)
+        {
+            return Err(DbError::InvalidDbHead)?;
+        }
+
+        let snake_len = snake.seek(SeekFrom::End(0))?;
+
+        let (_, prefix, _) = db_name(&snake_path);
+        let name = snake_head.name().to_string();
+        let prefix = if name == prefix { "" } else { prefix };
+
+        let mut db = Self {
+            index,
+            snake,
+            prefix: prefix.to_string(),
+            name,
+            revision: snake_head.revision,
+            snake_len,
+            head_total: 0,
+            head_skip: Value::new(0),
+            head_show: Value::new(10),
+            heads: Vec::with_capacity(10),
+            active_head: None,
+            payload_skip: Value::new(0),
+            payload: Vec::with_capacity(PAYLOAD_PAGE as usize),
+            payload_error: None,
+        };
+
+        db.update()?;
+
+        Ok(db)
+    }
+
+    fn update(&mut self) -> Result<()> {
+        self.snake_len = self.snake.seek(SeekFrom::End(0))?;
+        let pos = self.index.seek(SeekFrom::End(0))?;
+        self.head_total = pos.saturating_sub(ENTITY_META) / SnakeHead::N;
+        self.update_heads()
+    }
+
+    fn update_heads(&mut self) -> Result<()> {
+        let skip = self.head_skip.main();
+        let max = (skip + self.head_show.main()).min(self.head_total);
+        self.heads.clear();
+        for id in skip..max {
+            let mut head = SnakeHead::default();
+            let pos = ENTITY_META + id * SnakeHead::N;
+            self.index.read_exact_at(head.as_binary_mut(), pos)?;
+            self.heads.push((id, head));
+        }
+        Ok(())
+    }
+
+    /// reads one page of the active head payload, never the whole snake
+    fn update_payload(&mut self) {
+        self.payload.clear();
+        self.payload_error = None;
+        let Some((_, head)) = &self.active_head else { return };
+
+        let start = head.position;
+        let Some(end) = head.position.checked_add(head.capacity) else {
+            self.payload_error = Some(format!(
+                "capacity out of bound: {start} + {} overflows",
+                head.capacity
+            ));
+            return;
+        };
+        if start >= self.snake_len {
+            self.payload_error = Some(format!(
+                "position out of bound: {start} >= {}",
+                self.snake_len
+            ));
+            return;
+        }
+        if end > self.snake_len {
+            self.payload_error = Some(format!(
+                "capacity out of bound: {end} > {} | available capacity: {}",
+                self.snake_len,
+                self.snake_len - start
+            ));
+            return;
+        }
+
+        let length = head.length.min(head.capacity);
+        let skip = self.payload_skip.main().min(length);
+        let size = (length - skip).min(PAYLOAD_PAGE) as usize;
+        self.payload.resize(size, 0);
+        if let Err(e) =
+            self.snake.read_exact_at(&mut self.payload, start + skip)
+        {
+            self.payload.clear();
+            self.payload_error = Some(format!("could not read payload: {e}"));
+        }
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        if self.head_skip.changed() || self.head_show.changed() {
+            if let Err(e) = self.update_heads() {
+                log::error!("could not read snake heads: {e:#?}");
+            }
+        }
+        if self.payload_skip.changed() {
+            self.update_payload();
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label(format!("db: {}.{}", self.name, self.revision));
+            ui.label(format!("snake size: {}", self.snake_len));
+            ui.label(format!("total heads: {}", self.head_total));
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::Slider::new(
+                    self.head_skip.deref_mut(),
+                    0..=self.head_total,
+                )
+                .text("skip"),
+            );
+            ui.separator();
+            ui.add(
+                egui::Slider::new(
+                    self.head_show.deref_mut(),
+                    0..=self.head_total,
+                )
+                .text("show"),
+            );
+        });
+        ui.separator();
+
+        egui::Frame::new()
+            .stroke(ui.style().visuals.window_stroke)
+            .show(ui, |ui| self.show_heads(ui));
+
+        self.show_active_head(ui);
+    }
+
+    fn show_heads(&mut self, ui: &mut egui::Ui) {
+        let mut clicked = None;
+        ee::TableBuilder::new(ui)
+            .id_salt("snake-heads")
+            .column(ee::Column::auto().resizable(true).at_least(50.0))
+            .columns(
+                ee::Column::remainder().resizable(true).clip(true),
+                HEAD_COLUMNS.len() - 1,
+            )
+            .striped(true)
+            .resizable(true)
+            .sense(egui::Sense::click())
+            .header(25.0, |mut header| {
+                for h in HEAD_COLUMNS {
+                    header.col(|ui| {
+                        ui.heading(h);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, self.heads.len(), |mut row| {
+                    let (id, head) = &self.heads[row.index()];
+                    let active = self
+                        .active_head
+                        .as_ref()
+                        .is_some_and(|(aid, _)| aid == id);
+                    row.set_selected(active);
+                    row.col(|ui| {
+                        ui.label(id.to_string());
+                    });
+                    row.col(|ui| {
+                        let g = &head.gene;
+                        ui.label(format!(
+                            "Gene({}, {}, {:?}, {})",
+                            g.id, g.iter, g.pepper, g.server
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.label(head.position.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(head.capacity.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(head.length.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.add(utils::ColoredBool::new("", head.is_alive()));
+                    });
+                    row.col(|ui| {
+                        ui.add(utils::ColoredBool::new("", head.is_free()));
+                    });
+                    if row.response().clicked() {
+                        clicked = Some((*id, *head));
+                    }
+                });
+            });
+
+        let Some((id, head)) = clicked else { return };
+        if self.active_head.as_ref().is_some_and(|(aid, _)| *aid == id) {
+            self.active_head = None;
+        } else {
+            self.active_head = Some((id, head));
+        }
+        self.payload_skip = Value::new(0);
+        self.update_payload();
+    }
+
+    fn show_active_head(&mut self, ui: &mut egui::Ui) {
+        let Some((id, head)) = self.active_head else { return };
+
+        egui::Frame::default()
+            .fill(ui.style().visuals.window_fill)
+            .stroke(utils::stroke(
+                head.is_alive(),
+                head.is_free(),
+                ui.visuals(),
+            ))
+            .inner_margin(8.0)
+            .outer_margin(8.0)
+            .corner_radius(5.0)
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(format!("head: {id}"));
+                    utils::gene(&head.gene, "Gene", ui);
+                    ui.label(format!("position: {}", head.position));
+                    ui.label(format!("capacity: {}", head.capacity));
+                    ui.label(format!("length: {}", head.length));
+                    ui.add(utils::ColoredBool::new("is free", head.is_free()));
+                    ui.add(utils::ColoredBool::new(
+                        "is alive",
+                        head.is_alive(),
+                    ));
+                });
+                ui.separator();
+
+                if let Some(err) = &self.payload_error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                    return;
+                }
+
+                let length = head.length.min(head.capacity);
+                if length > PAYLOAD_PAGE {
+                    ui.add(
+                        egui::Slider::new(
+                            self.payload_skip.deref_mut(),
+                            0..=length - 1,
+                        )
+                        .step_by(utils::HEX_ROW as f64)
+                        .text("offset"),
+                    );
+                }
+
+                let skip = self.payload_skip.main();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Wrap);
+                    ui.label(String::from_utf8_lossy(&self.payload));
+                    ui.separator();
+                    let rows = self.payload.chunks(utils::HEX_ROW);
+                    for (i, row) in rows.enumerate() {
+                        let offset = skip + (i * utils::HEX_ROW) as u64;
+                        ui.monospace(utils::hex_row(offset, row));
+                    }
+                });
+            });
+    }
+}
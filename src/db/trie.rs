@@ -0,0 +1,641 @@
+//! viewer for shah's trie. every node is a value followed by one position
+//! per char of the abc and the root node is right after the meta. a key
+//! walks one char per node from the root, its value is in the node of its
+//! last char. the file does not store the size of the value, it is guessed
+//! from the positions in the nodes
+
+use super::worker::Worker;
+use super::{nav, Database, Value};
+use crate::Result;
+use shah::db::trie::TrieMeta;
+use shah::models::{Binary, Gene};
+use shah::{AsUtf8Str, DbError};
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::{fs::OpenOptions, os::unix::fs::FileExt, path::PathBuf};
+
+/// keys past this are not listed by a prefix scan
+const PREFIX_LIMIT: usize = 10_000;
+/// largest value size that is tried when guessing
+const MAX_VALUE: u64 = 4096;
+/// nodes that are read to check a guessed value size
+const GUESS_NODES: usize = 256;
+
+type Scanned = std::result::Result<PrefixScan, String>;
+
+/// the layout of a trie file
+#[derive(Debug, Clone)]
+struct Trie {
+    abc: Vec<char>,
+    /// bytes of the value at the start of every node
+    value: u64,
+    file_size: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    value: Vec<u8>,
+    child: Vec<u64>,
+}
+
+/// one node on the way of a lookup, the position stored in it for `char`
+#[derive(Debug, Clone)]
+struct Hop {
+    pos: u64,
+    char: char,
+    next: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Lookup {
+    key: String,
+    hops: Vec<Hop>,
+    /// the value of the node of the last char, None if the walk stopped
+    /// at an empty position
+    value: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+struct PrefixScan {
+    prefix: String,
+    keys: Vec<(String, Vec<u8>)>,
+    /// true if the scan stopped at PREFIX_LIMIT
+    more: bool,
+}
+
+fn is_zero(value: &[u8]) -> bool {
+    value.iter().all(|b| *b == 0)
+}
+
+impl Trie {
+    fn node_size(&self) -> u64 {
+        self.value + self.abc.len() as u64 * 8
+    }
+
+    /// nodes in the file, the root included
+    fn nodes(&self) -> u64 {
+        self.file_size.saturating_sub(TrieMeta::N) / self.node_size()
+    }
+
+    /// true if `pos` is the start of a node
+    fn is_node(&self, pos: u64) -> bool {
+        pos >= TrieMeta::N
+            && (pos - TrieMeta::N).is_multiple_of(self.node_size())
+            && pos + self.node_size() <= self.file_size
+    }
+
+    fn indices(&self, key: &str) -> std::result::Result<Vec<usize>, String> {
+        key.chars()
+            .map(|c| {
+                self.abc
+                    .iter()
+                    .position(|a| *a == c)
+                    .ok_or_else(|| format!("{c:?} is not in the abc"))
+            })
+            .collect()
+    }
+
+    fn read(&self, file: &File, pos: u64) -> std::result::Result<Node, String> {
+        let mut buf = vec![0u8; self.node_size() as usize];
+        file.read_exact_at(&mut buf, pos)
+            .map_err(|e| format!("could not read the node at {pos}: {e}"))?;
+        let (value, child) = buf.split_at(self.value as usize);
+        Ok(Node {
+            value: value.to_vec(),
+            child: child
+                .chunks_exact(8)
+                .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+                .collect(),
+        })
+    }
+
+    /// true if the file is whole nodes and the first nodes from the root
+    /// only point to the start of other nodes
+    fn fits(&self, file: &File) -> bool {
+        let size = self.file_size.saturating_sub(TrieMeta::N);
+        if size == 0 || !size.is_multiple_of(self.node_size()) {
+            return false;
+        }
+        let mut queue = std::collections::VecDeque::from([TrieMeta::N]);
+        let mut read = 0;
+        while let Some(pos) = queue.pop_front() {
+            if read == GUESS_NODES {
+                break;
+            }
+            read += 1;
+            let Ok(node) = self.read(file, pos) else { return false };
+            for next in node.child.into_iter().filter(|p| *p != 0) {
+                if !self.is_node(next) || next == TrieMeta::N {
+                    return false;
+                }
+                queue.push_back(next);
+            }
+        }
+        true
+    }
+
+    /// the smallest value size that fits the file
+    fn guess(abc: &[char], file: &File, file_size: u64) -> Option<u64> {
+        (1..=MAX_VALUE).find(|value| {
+            let trie = Trie { abc: abc.to_vec(), value: *value, file_size };
+            trie.fits(file)
+        })
+    }
+
+    /// walks `key` from the root to its node. the walk stops at the first
+    /// empty position
+    fn lookup(
+        &self, file: &File, key: &str,
+    ) -> std::result::Result<Lookup, String> {
+        let indices = self.indices(key)?;
+        if indices.is_empty() {
+            return Err("a key has at least one char".into());
+        }
+        let mut hops = Vec::with_capacity(indices.len());
+        let mut pos = TrieMeta::N;
+        for (char, ki) in key.chars().zip(indices) {
+            let next = self.read(file, pos)?.child[ki];
+            hops.push(Hop { pos, char, next });
+            if next == 0 {
+                return Ok(Lookup { key: key.to_string(), hops, value: None });
+            }
+            pos = next;
+        }
+        let value = Some(self.read(file, pos)?.value).filter(|v| !is_zero(v));
+        Ok(Lookup { key: key.to_string(), hops, value })
+    }
+
+    /// the nodes on the way to the node of `prefix` as (prefix, pos), from
+    /// the root down. it stops at the first empty position
+    fn path(
+        &self, file: &File, prefix: &str,
+    ) -> std::result::Result<Vec<(String, u64)>, String> {
+        let indices = self.indices(prefix)?;
+        let mut path = vec![(String::new(), TrieMeta::N)];
+        let mut key = String::new();
+        for (char, ki) in prefix.chars().zip(indices) {
+            let pos = path.last().unwrap().1;
+            let next = self.read(file, pos)?.child[ki];
+            if next == 0 {
+                break;
+            }
+            key.push(char);
+            path.push((key.clone(), next));
+        }
+        Ok(path)
+    }
+
+    /// every key that starts with `prefix` and has a value, in abc order
+    fn prefix(&self, file: &File, prefix: &str) -> Scanned {
+        let mut scan = PrefixScan {
+            prefix: prefix.to_string(),
+            keys: Vec::new(),
+            more: false,
+        };
+        let path = self.path(file, prefix)?;
+        let (found, pos) = path.last().unwrap().clone();
+        if found.chars().count() < prefix.chars().count() {
+            return Ok(scan);
+        }
+        let mut key = found.chars().collect();
+        // a whole trie reads every node once, more means it loops
+        let mut budget = self.nodes();
+        self.walk(file, pos, &mut key, &mut budget, &mut scan)?;
+        Ok(scan)
+    }
+
+    /// lists the values of the node at `pos` and every node under it,
+    /// `key` has the chars on the way to it
+    fn walk(
+        &self, file: &File, pos: u64, key: &mut Vec<char>, budget: &mut u64,
+        scan: &mut PrefixScan,
+    ) -> std::result::Result<(), String> {
+        if *budget == 0 {
+            return Err(format!("the node at {pos} loops back"));
+        }
+        *budget -= 1;
+        let node = self.read(file, pos)?;
+        if !key.is_empty() && !is_zero(&node.value) {
+            if scan.keys.len() >= PREFIX_LIMIT {
+                scan.more = true;
+                return Ok(());
+            }
+            scan.keys.push((key.iter().collect(), node.value));
+        }
+        for (ki, next) in node.child.into_iter().enumerate() {
+            if next == 0 {
+                continue;
+            }
+            key.push(self.abc[ki]);
+            self.walk(file, next, key, budget, scan)?;
+            key.pop();
+            if scan.more {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// a gene link if the value is the size of a gene, hex bytes otherwise
+fn show_value(value: &[u8], ui: &mut egui::Ui) {
+    let hex = value.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>();
+    if is_zero(value) {
+        ui.label("---");
+    } else if value.len() == Gene::S {
+        nav::gene_link(Gene::from_binary(value), ui)
+            .on_hover_text(hex.join(" "));
+    } else {
+        ui.monospace(hex.join(" "));
+    }
+}
+
+pub struct TrieDb {
+    file: File,
+    name: String,
+    trie: Trie,
+    value_size: Value<u64>,
+    /// the value size was guessed from the file
+    guessed: bool,
+    fits: bool,
+    /// the nodes from the root down to the open node as (key prefix,
+    /// position)
+    crumbs: Vec<(String, u64)>,
+    node: Option<std::result::Result<Node, String>>,
+    key_query: String,
+    lookup: Option<std::result::Result<Lookup, String>>,
+    /// lists the keys under a prefix
+    prefix_worker: Worker<(Trie, String), Scanned>,
+    prefix: Option<Scanned>,
+}
+
+impl Database for TrieDb {
+    fn init(path: PathBuf) -> Result<Self> {
+        let mut file = OpenOptions::new().read(true).open(&path)?;
+        let mut meta = TrieMeta::default();
+        file.read_exact_at(meta.as_binary_mut(), 0)?;
+
+        let abc_len = meta.abc_len as usize;
+        if abc_len == 0 || abc_len >= meta.abc.len() {
+            return Err(DbError::InvalidDbMeta)?;
+        }
+        let abc = meta.abc[..abc_len].as_utf8_str().chars().collect::<Vec<_>>();
+        let file_size = file.seek(SeekFrom::End(0))?;
+        let guess = Trie::guess(&abc, &file, file_size);
+        let value = guess.unwrap_or(Gene::S as u64);
+
+        let reader = file.try_clone()?;
+        let prefix_worker =
+            Worker::start(move |(trie, prefix): (Trie, String)| {
+                trie.prefix(&reader, &prefix)
+            });
+
+        let mut db = Self {
+            file,
+            name: meta.db.name().to_string(),
+            trie: Trie { abc, value, file_size },
+            value_size: Value::new(value),
+            guessed: guess.is_some(),
+            fits: guess.is_some(),
+            crumbs: vec![(String::new(), TrieMeta::N)],
+            node: None,
+            key_query: String::new(),
+            lookup: None,
+            prefix_worker,
+            prefix: None,
+        };
+        db.read_node();
+
+        Ok(db)
+    }
+
+    fn title(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn reload(&mut self) {
+        match self.file.seek(SeekFrom::End(0)) {
+            Ok(size) => self.trie.file_size = size,
+            Err(e) => log::error!("could not reload trie db: {e}"),
+        }
+        self.fits = self.trie.fits(&self.file);
+        self.read_node();
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        if self.value_size.changed() {
+            self.set_value_size();
+        }
+
+        ui.label("trie db");
+        ui.label(format!("abc: {} | {:?}", self.trie.abc.len(), self.trie.abc));
+        ui.horizontal(|ui| {
+            ui.label("value size:");
+            ui.add(
+                egui::DragValue::new(&mut *self.value_size)
+                    .range(0..=MAX_VALUE),
+            );
+            if !self.fits {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    "the nodes do not fit this size",
+                );
+            } else if self.guessed {
+                ui.weak("guessed");
+            }
+        });
+        ui.label(format!(
+            "node: {} bytes | {} nodes | file size: {}",
+            self.trie.node_size(),
+            self.trie.nodes(),
+            self.trie.file_size
+        ));
+        self.show_lookup(ui);
+        self.show_node(ui);
+    }
+}
+
+impl TrieDb {
+    /// reads the nodes again with the value size of the input
+    fn set_value_size(&mut self) {
+        self.trie.value = self.value_size.main();
+        self.guessed = false;
+        self.fits = self.trie.fits(&self.file);
+        self.crumbs.truncate(1);
+        self.lookup = None;
+        self.prefix = None;
+        self.read_node();
+    }
+
+    fn read_node(&mut self) {
+        self.node =
+            self.crumbs.last().map(|(_, pos)| self.trie.read(&self.file, *pos));
+    }
+
+    /// opens the deepest node on the way to `prefix`
+    fn open_prefix(&mut self, prefix: &str) {
+        match self.trie.path(&self.file, prefix) {
+            Ok(path) => {
+                self.crumbs = path;
+                self.read_node();
+            }
+            Err(e) => self.lookup = Some(Err(e)),
+        }
+    }
+
+    fn show_lookup(&mut self, ui: &mut egui::Ui) {
+        if let Some(res) = self.prefix_worker.poll() {
+            self.prefix = Some(res);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("key:");
+            let res = ui.add(
+                egui::TextEdit::singleline(&mut self.key_query)
+                    .desired_width(160.0),
+            );
+            let enter = res.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("find").clicked() || enter {
+                self.lookup =
+                    Some(self.trie.lookup(&self.file, &self.key_query));
+            }
+            if ui
+                .button("open")
+                .on_hover_text("the deepest node on the way to this key")
+                .clicked()
+            {
+                self.open_prefix(&self.key_query.clone());
+            }
+            if ui
+                .button("list prefix")
+                .on_hover_text("every key that starts with this")
+                .clicked()
+            {
+                let req = (self.trie.clone(), self.key_query.clone());
+                self.prefix_worker.request(req, ui.ctx());
+            }
+            if self.prefix_worker.busy() {
+                ui.spinner();
+            }
+        });
+
+        match self.lookup.clone() {
+            Some(Ok(lookup)) => self.show_hops(&lookup, ui),
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            None => {}
+        }
+
+        let scan = match &self.prefix {
+            Some(Ok(scan)) => scan,
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                return;
+            }
+            None => return,
+        };
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} keys under {:?}",
+                scan.keys.len(),
+                scan.prefix
+            ));
+            if scan.more {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("only the first {PREFIX_LIMIT} are listed"),
+                );
+            }
+        });
+        let height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .id_salt("prefix keys")
+            .max_height(height * 12.0)
+            .show_rows(ui, height, scan.keys.len(), |ui, range| {
+                for (key, value) in &scan.keys[range] {
+                    ui.horizontal(|ui| {
+                        ui.monospace(key);
+                        show_value(value, ui);
+                    });
+                }
+            });
+    }
+
+    fn show_hops(&mut self, lookup: &Lookup, ui: &mut egui::Ui) {
+        for (i, hop) in lookup.hops.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let text = if i == 0 {
+                    "root".to_string()
+                } else {
+                    format!("node {}", hop.pos)
+                };
+                if ui.button(text).clicked() {
+                    let under = lookup.key.chars().take(i);
+                    self.open_prefix(&under.collect::<String>());
+                }
+                ui.label(format!("[{}] ->", hop.char));
+                if hop.next == 0 {
+                    ui.label("---");
+                } else {
+                    ui.label(hop.next.to_string());
+                }
+            });
+        }
+        match &lookup.value {
+            Some(value) => {
+                ui.horizontal(|ui| {
+                    ui.label("value:");
+                    show_value(value, ui);
+                });
+            }
+            None => {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("{:?} has no value", lookup.key),
+                );
+            }
+        }
+    }
+
+    fn show_node(&mut self, ui: &mut egui::Ui) {
+        let Some(node) = self.node.clone() else { return };
+        ui.separator();
+
+        let mut open = None;
+        ui.horizontal(|ui| {
+            for (i, (prefix, pos)) in self.crumbs.iter().enumerate() {
+                let text = if i == 0 {
+                    "root".to_string()
+                } else {
+                    ui.label(">");
+                    prefix.chars().last().unwrap_or_default().to_string()
+                };
+                let current = i + 1 == self.crumbs.len();
+                if ui
+                    .selectable_label(current, text)
+                    .on_hover_text(format!("node {pos} under {prefix:?}"))
+                    .clicked()
+                {
+                    open = Some(i);
+                }
+            }
+        });
+        if let Some(i) = open {
+            self.crumbs.truncate(i + 1);
+            self.read_node();
+        }
+
+        let node = match node {
+            Ok(node) => node,
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                return;
+            }
+        };
+        let prefix = self.crumbs.last().map(|(p, _)| p.clone());
+        let prefix = prefix.unwrap_or_default();
+        ui.horizontal(|ui| {
+            ui.label("value:");
+            show_value(&node.value, ui);
+        });
+        let used = node.child.iter().filter(|p| **p != 0).count();
+        ui.label(format!("children: {used}/{}", node.child.len()));
+
+        let mut down = None;
+        egui::ScrollArea::vertical().id_salt("trie node").show(ui, |ui| {
+            for (c, v) in self.trie.abc.iter().zip(node.child) {
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("{prefix}{c}"));
+                    ui.label(
+                        egui::RichText::new(":").color(egui::Color32::GOLD),
+                    );
+                    if v == 0 {
+                        ui.label("---");
+                    } else if ui.button(v.to_string()).clicked() {
+                        down = Some((format!("{prefix}{c}"), v));
+                    }
+                });
+            }
+        });
+        if let Some(next) = down {
+            self.crumbs.push(next);
+            self.read_node();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, TAGS};
+    use crate::snapshot;
+
+    fn open() -> TrieDb {
+        TrieDb::init(fixtures::get().tags.clone()).unwrap()
+    }
+
+    #[test]
+    fn guess() {
+        let db = open();
+        assert!(db.guessed);
+        assert_eq!(db.trie.value, Gene::S as u64);
+        // root, a, ab, abc, c, ca, d, dd
+        assert_eq!(db.trie.nodes(), 8);
+        let wrong = Trie { value: 8, ..db.trie.clone() };
+        assert!(!wrong.fits(&db.file));
+    }
+
+    #[test]
+    fn lookup() {
+        let db = open();
+        for (key, id) in TAGS {
+            let lookup = db.trie.lookup(&db.file, key).unwrap();
+            assert_eq!(lookup.hops.len(), key.len(), "{key}");
+            let value = lookup.value.expect(key);
+            assert_eq!(*Gene::from_binary(&value), fixtures::gene(id), "{key}");
+        }
+        // "c" is only on the way to "ca"
+        assert!(db.trie.lookup(&db.file, "c").unwrap().value.is_none());
+        let lookup = db.trie.lookup(&db.file, "bad").unwrap();
+        assert!(matches!(lookup.hops[..], [Hop { char: 'b', next: 0, .. }]));
+        assert!(db.trie.lookup(&db.file, "").is_err());
+        assert!(db.trie.lookup(&db.file, "ax").is_err());
+    }
+
+    #[test]
+    fn prefix() {
+        let db = open();
+        let keys = |p: &str| {
+            let scan = db.trie.prefix(&db.file, p).unwrap();
+            scan.keys.into_iter().map(|(k, _)| k).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(""), TAGS.map(|(k, _)| k));
+        assert_eq!(keys("ab"), ["ab", "abc"]);
+        assert_eq!(keys("c"), ["ca"]);
+        assert!(keys("abd").is_empty());
+        assert!(keys("b").is_empty());
+
+        let path = db.trie.path(&db.file, "abd").unwrap();
+        let prefixes = path.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>();
+        assert_eq!(prefixes, ["", "a", "ab"]);
+    }
+
+    #[test]
+    fn show() {
+        let mut db = open();
+        db.key_query = "ab".to_string();
+        db.lookup = Some(db.trie.lookup(&db.file, "abc"));
+        db.open_prefix("ab");
+        let mut sent = false;
+        let text = snapshot::render(|ui| {
+            if !std::mem::replace(&mut sent, true) {
+                let req = (db.trie.clone(), db.key_query.clone());
+                db.prefix_worker.request(req, ui.ctx());
+            }
+            db.show(ui)
+        });
+        snapshot::check("trie_show", &text);
+    }
+}
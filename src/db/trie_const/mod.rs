@@ -0,0 +1,876 @@
+mod stats;
+mod walk;
+
+use super::worker::Worker;
+use super::{nav, Database, TileState, Value};
+use crate::Result;
+use shah::db::trie_const::TrieConstMeta;
+use shah::models::{Binary, Gene};
+use shah::{AsUtf8Str, DbError};
+use stats::{StatsScan, TrieStats};
+use std::fs::File;
+use std::io::Write;
+use std::ops::DerefMut;
+use std::sync::atomic::AtomicBool;
+use std::{fs::OpenOptions, os::unix::fs::FileExt, path::PathBuf};
+use walk::{Hop, Lookup, PrefixScan, Trie, PREFIX_LIMIT};
+
+/// most cache slots that are read and listed at once
+const CACHE_PAGE: u64 = 4096;
+
+type CachePage = std::result::Result<(u64, Vec<u64>), String>;
+type Scanned = std::result::Result<PrefixScan, String>;
+type Found = std::result::Result<Lookup, String>;
+/// the breadcrumbs and the entries of the opened node
+type Walked = std::result::Result<(Vec<(String, u64)>, Option<Node>), String>;
+type Node = std::result::Result<Entries, String>;
+
+/// the positions of an index node, or the genes of the last one
+#[derive(Clone)]
+enum Entries {
+    Nodes(Vec<u64>),
+    Genes(Vec<Gene>),
+}
+
+/// what the node worker opens
+enum NodeReq {
+    /// the last node of the path, the nodes above it are its breadcrumbs
+    Path(Vec<(String, u64)>),
+    /// the deepest node on the way to the prefix
+    Prefix(String),
+}
+
+fn walk(trie: &Trie, file: &File, req: NodeReq) -> Walked {
+    let crumbs = match req {
+        NodeReq::Path(path) => path,
+        NodeReq::Prefix(prefix) => trie.path(file, &prefix)?,
+    };
+    let node = crumbs.last().map(|(prefix, pos)| {
+        if trie.is_leaf(prefix.chars().count()) {
+            trie.read_leaf(file, *pos).map(Entries::Genes)
+        } else {
+            trie.read_node(file, *pos).map(Entries::Nodes)
+        }
+    });
+    Ok((crumbs, node))
+}
+
+fn read_cache(file: &File, skip: u64, count: usize) -> CachePage {
+    let mut buf = vec![0u8; count * 8];
+    let pos = TrieConstMeta::N + skip * 8;
+    file.read_exact_at(&mut buf, pos)
+        .map_err(|e| format!("could not read cache at {skip}: {e}"))?;
+    let data = buf
+        .chunks_exact(8)
+        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    Ok((skip, data))
+}
+
+pub struct TrieConstDb {
+    file: File,
+    name: String,
+    index: u64,
+    cache: u64,
+    abc: Vec<char>,
+    cache_len: u64,
+    cache_skip: Value<u64>,
+    cache_show: Value<u64>,
+    cache_data: Vec<u64>,
+    /// reads (skip, count) cache slots, returns skip and the slots
+    cache_worker: Worker<(u64, usize), CachePage>,
+    cache_stale: bool,
+    cache_error: Option<String>,
+    /// (slot, key prefix, node position) of the listed slots
+    cached_cache_ui: Vec<(u64, String, u64)>,
+    /// the nodes from a cache slot down to the open node as
+    /// (key prefix, position)
+    crumbs: Vec<(String, u64)>,
+    node: Option<Node>,
+    /// reads the node of a path or a prefix
+    node_worker: Worker<NodeReq, Walked>,
+    /// the node to open in the next frame
+    node_req: Option<NodeReq>,
+    jump_query: String,
+    jump_error: Option<String>,
+    /// the query of the jump that is being opened
+    jumping: Option<String>,
+    trie: Trie,
+    key_query: String,
+    lookup: Option<Found>,
+    lookup_worker: Worker<String, Found>,
+    /// lists the keys under a prefix
+    prefix_worker: Worker<String, Scanned>,
+    prefix: Option<Scanned>,
+    stats: Option<StatsScan>,
+}
+
+impl Database for TrieConstDb {
+    fn title(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn reload(&mut self) {
+        self.update_cache_data();
+        self.read_node();
+    }
+
+    fn state(&self) -> TileState {
+        TileState {
+            skip: Some(self.cache_skip.main()),
+            show: Some(self.cache_show.main()),
+            ..Default::default()
+        }
+    }
+
+    fn restore(&mut self, state: &TileState, _: &egui::Context) {
+        if let Some(skip) = state.skip {
+            *self.cache_skip = skip;
+        }
+        if let Some(show) = state.show {
+            *self.cache_show = show;
+        }
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        self.poll_cache();
+        self.poll_node();
+        if let Some(req) = self.node_req.take() {
+            self.node_worker.request(req, ui.ctx());
+        }
+        if self.cache_skip.changed() || self.cache_show.changed() {
+            self.update_cache_data();
+        }
+        if std::mem::take(&mut self.cache_stale) {
+            let skip = self.cache_skip.main();
+            let m = self.cache_show.min(self.cache_len - skip) as usize;
+            self.cache_worker.request((skip, m), ui.ctx());
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("trie const db");
+            if self.cache_worker.busy() || self.node_worker.busy() {
+                ui.spinner();
+            }
+        });
+        if let Some(e) = &self.cache_error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+        ui.label(format!("abc: {} | {:?}", self.abc.len(), self.abc));
+        ui.label(format!(
+            "cache + index = len | {} + {} = {}",
+            self.cache,
+            self.index,
+            self.cache + self.index
+        ));
+        self.show_stats(ui);
+        self.show_lookup(ui);
+        self.show_jump(ui);
+
+        ui.add(
+            egui::Slider::new(
+                self.cache_skip.deref_mut(),
+                0..=self.cache_len - 1,
+            )
+            .text("cache skip")
+            .drag_value_speed(1000.0),
+        );
+        ui.add(
+            egui::Slider::new(
+                self.cache_show.deref_mut(),
+                1..=self.cache_len.min(CACHE_PAGE),
+            )
+            .text("cache show")
+            .drag_value_speed(1000.0),
+        );
+
+        self.show_node(ui);
+
+        ui.separator();
+        egui::ScrollArea::vertical().show(ui, |ui| self.show_cache(ui));
+    }
+
+    fn init(path: PathBuf) -> Result<Self> {
+        let file = OpenOptions::new().read(true).open(&path)?;
+        let mut meta = TrieConstMeta::default();
+        file.read_exact_at(meta.as_binary_mut(), 0)?;
+
+        let abc_len = meta.abc_len as usize;
+        if abc_len == 0 || abc_len >= meta.abc.len() {
+            return Err(DbError::InvalidDbMeta)?;
+        }
+
+        let abc = meta.abc[..abc_len].as_utf8_str().chars().collect::<Vec<_>>();
+        // only a page of the cache is read at a time, it can be any size
+        // that still has a position in the file
+        let cache_len = u32::try_from(meta.cache)
+            .ok()
+            .and_then(|c| (abc.len() as u64).checked_pow(c))
+            .filter(|n| n.checked_mul(8).is_some())
+            .ok_or(DbError::InvalidDbMeta)?;
+
+        let reader = file.try_clone()?;
+        let cache_worker = Worker::start(move |(skip, count)| {
+            read_cache(&reader, skip, count)
+        });
+        let trie =
+            Trie { abc: abc.clone(), cache: meta.cache, index: meta.index };
+        let reader = file.try_clone()?;
+        let walker = trie.clone();
+        let prefix_worker = Worker::start(move |prefix: String| {
+            walker.prefix(&reader, &prefix)
+        });
+        let reader = file.try_clone()?;
+        let walker = trie.clone();
+        let node_worker =
+            Worker::start(move |req: NodeReq| walk(&walker, &reader, req));
+        let reader = file.try_clone()?;
+        let walker = trie.clone();
+        let lookup_worker =
+            Worker::start(move |key: String| walker.lookup(&reader, &key));
+
+        let mut db = Self {
+            file,
+            cache: meta.cache,
+            index: meta.index,
+            abc,
+            name: meta.db.name().to_string(),
+            cache_len,
+            cache_skip: Value::new(0),
+            cache_show: Value::new(10),
+            cache_data: Vec::new(),
+            cached_cache_ui: Vec::new(),
+            cache_worker,
+            cache_stale: false,
+            cache_error: None,
+            crumbs: Vec::new(),
+            node: None,
+            node_worker,
+            node_req: None,
+            jump_query: String::new(),
+            jump_error: None,
+            jumping: None,
+            trie,
+            key_query: String::new(),
+            lookup: None,
+            lookup_worker,
+            prefix_worker,
+            prefix: None,
+            stats: None,
+        };
+
+        db.update_cache_data();
+
+        Ok(db)
+    }
+}
+
+impl TrieConstDb {
+    /// the head of the database and the same analysis the view runs
+    pub fn write_stats(
+        &self, out: &mut impl Write,
+    ) -> std::result::Result<(), String> {
+        let stop = AtomicBool::new(false);
+        let stats = stats::scan(&self.trie, &self.file, &stop, |_| {})?;
+        let io = |e: std::io::Error| e.to_string();
+        writeln!(out, "db: {}", self.name).map_err(io)?;
+        writeln!(
+            out,
+            "abc: {} chars | cache: {} chars | index: {} levels",
+            self.abc.len(),
+            self.cache,
+            self.index
+        )
+        .map_err(io)?;
+        for line in stats.summary() {
+            writeln!(out, "{line}").map_err(io)?;
+        }
+        if stats.broken > 0 {
+            writeln!(
+                out,
+                "{} positions point outside of the file",
+                stats.broken
+            )
+            .map_err(io)?;
+        }
+        for (i, level) in stats.levels.iter().enumerate() {
+            writeln!(
+                out,
+                "level {} (under {} chars): {} nodes",
+                i + 1,
+                self.cache as usize + i,
+                level.nodes
+            )
+            .map_err(io)?;
+            for (n, count) in level.fanout.iter().enumerate() {
+                if *count > 0 {
+                    writeln!(out, "{n:>5}: {count}").map_err(io)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// queues a read of the visible cache slots
+    fn update_cache_data(&mut self) {
+        self.cache_skip = Value::new(self.cache_skip.min(self.cache_len - 1));
+        let page = self.cache_len.min(CACHE_PAGE);
+        self.cache_show = Value::new(self.cache_show.clamp(1, page));
+        self.cache_stale = true;
+    }
+
+    fn poll_cache(&mut self) {
+        let (skip, data) = match self.cache_worker.poll() {
+            Some(Ok(page)) => page,
+            Some(Err(e)) => {
+                self.cache_error = Some(e);
+                return;
+            }
+            None => return,
+        };
+        self.cache_error = None;
+        self.cache_data = data;
+
+        self.cached_cache_ui.clear();
+        let mut did_wrote_zero = false;
+        let len = self.cache_data.len();
+        for (i, p) in self.cache_data.iter().enumerate() {
+            let idx = skip + i as u64;
+            let is_last = i + 1 == len;
+            if did_wrote_zero && *p == 0 && !is_last {
+                continue;
+            }
+
+            let prefix = self.trie.slot_prefix(idx);
+            self.cached_cache_ui.push((idx, prefix, *p));
+            did_wrote_zero = *p == 0;
+        }
+    }
+
+    /// opens the last node of `path`, the nodes above it are its
+    /// breadcrumbs
+    fn open_path(&mut self, path: Vec<(String, u64)>) {
+        self.jumping = None;
+        self.node_req = Some(NodeReq::Path(path));
+    }
+
+    /// opens the deepest node on the way to `prefix`
+    fn open_prefix(&mut self, prefix: &str) {
+        self.jumping = None;
+        self.node_req = Some(NodeReq::Prefix(prefix.to_string()));
+    }
+
+    /// reads the open node again
+    fn read_node(&mut self) {
+        self.open_path(self.crumbs.clone());
+    }
+
+    fn poll_node(&mut self) {
+        let (crumbs, node) = match self.node_worker.poll() {
+            Some(Ok(walked)) => walked,
+            Some(Err(e)) => {
+                self.jumping = None;
+                self.jump_error = Some(e);
+                return;
+            }
+            None => return,
+        };
+        self.crumbs = crumbs;
+        self.node = node;
+        let Some(query) = self.jumping.take() else { return };
+        let len = query.chars().count();
+        let found = self.crumbs.last().map(|(p, _)| p.chars().count());
+        if found < Some(len.min(self.trie.depth() - 1)) {
+            self.jump_error =
+                Some(format!("there are no keys under {query:?}"));
+        }
+    }
+
+    /// lists the cache slot of the prefix and opens its deepest node
+    fn jump(&mut self) {
+        self.jump_error = None;
+        let query = self.jump_query.clone();
+        let mut indices = match self.trie.indices(&query) {
+            Ok(v) => v,
+            Err(e) => {
+                self.jump_error = Some(e);
+                return;
+            }
+        };
+        let cache = self.cache as usize;
+        indices.resize(indices.len().max(cache), 0);
+        *self.cache_skip = self.trie.slot(&indices);
+
+        let len = query.chars().count();
+        if len < cache {
+            return;
+        }
+        let prefix = query.chars().take(self.trie.depth() - 1);
+        self.open_prefix(&prefix.collect::<String>());
+        self.jumping = Some(query);
+    }
+
+    fn show_jump(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("jump to:");
+            let res = ui.add(
+                egui::TextEdit::singleline(&mut self.jump_query)
+                    .hint_text("prefix")
+                    .desired_width(160.0),
+            );
+            let enter = res.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("go").clicked() || enter {
+                self.jump();
+            }
+            if let Some(e) = &self.jump_error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+        });
+    }
+
+    fn show_node(&mut self, ui: &mut egui::Ui) {
+        let Some(node) = self.node.clone() else { return };
+        ui.separator();
+
+        let mut open = None;
+        ui.horizontal(|ui| {
+            for (i, (prefix, pos)) in self.crumbs.iter().enumerate() {
+                let text = if i == 0 {
+                    prefix.clone()
+                } else {
+                    ui.label(">");
+                    prefix.chars().last().unwrap_or_default().to_string()
+                };
+                let current = i + 1 == self.crumbs.len();
+                if ui
+                    .selectable_label(current, text)
+                    .on_hover_text(format!("node {pos} under {prefix:?}"))
+                    .clicked()
+                {
+                    open = Some(i);
+                }
+            }
+        });
+        if let Some(i) = open {
+            self.open_path(self.crumbs[..=i].to_vec());
+        }
+
+        let prefix = self.crumbs.last().map(|(p, _)| p.clone());
+        let prefix = prefix.unwrap_or_default();
+        let mut down = None;
+        match node {
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            Ok(Entries::Nodes(list)) => {
+                for (c, v) in self.abc.iter().zip(list) {
+                    ui.horizontal(|ui| {
+                        ui.monospace(format!("{prefix}{c}"));
+                        ui.label(
+                            egui::RichText::new(":").color(egui::Color32::GOLD),
+                        );
+                        if v == 0 {
+                            ui.label("---");
+                        } else if ui.button(v.to_string()).clicked() {
+                            down = Some((format!("{prefix}{c}"), v));
+                        }
+                    });
+                }
+            }
+            Ok(Entries::Genes(list)) => {
+                for (c, g) in self.abc.iter().zip(list) {
+                    ui.horizontal(|ui| {
+                        ui.monospace(format!("{prefix}{c}"));
+                        ui.label(
+                            egui::RichText::new(":").color(egui::Color32::GOLD),
+                        );
+                        if g.is_none() {
+                            ui.label("---");
+                        } else {
+                            nav::gene_link(&g, ui);
+                        }
+                    });
+                }
+            }
+        }
+        if let Some(next) = down {
+            let mut path = self.crumbs.clone();
+            path.push(next);
+            self.open_path(path);
+        }
+    }
+
+    fn show_stats(&mut self, ui: &mut egui::Ui) {
+        if let Some(scan) = &mut self.stats {
+            scan.poll();
+        }
+        ui.horizontal(|ui| {
+            let busy = self.stats.as_ref().is_some_and(|s| !s.done());
+            let text =
+                if self.stats.is_some() { "analyze again" } else { "analyze" };
+            let res = ui
+                .add_enabled(!busy, egui::Button::new(text))
+                .on_hover_text("read every cache slot and index node");
+            if res.clicked() {
+                match self.file.try_clone() {
+                    Ok(file) => {
+                        let trie = self.trie.clone();
+                        let ctx = ui.ctx().clone();
+                        self.stats = Some(StatsScan::start(trie, file, ctx));
+                    }
+                    Err(e) => {
+                        self.stats = None;
+                        self.cache_error = Some(e.to_string());
+                    }
+                }
+            }
+            let Some(scan) = &self.stats else { return };
+            if busy {
+                ui.spinner();
+                ui.add(
+                    egui::ProgressBar::new(
+                        scan.scanned as f32 / scan.total as f32,
+                    )
+                    .desired_width(200.0)
+                    .text(format!("{}/{} slots", scan.scanned, scan.total)),
+                );
+            }
+            if ui.small_button("x").clicked() {
+                self.stats = None;
+            }
+        });
+
+        let stats = match self.stats.as_ref().and_then(|s| s.result.as_ref()) {
+            Some(Ok(stats)) => stats.clone(),
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                return;
+            }
+            None => return,
+        };
+        let colps = egui::CollapsingHeader::new("analysis")
+            .default_open(true)
+            .show_background(true);
+        colps.show(ui, |ui| self.show_analysis(&stats, ui));
+    }
+
+    fn show_analysis(&mut self, stats: &TrieStats, ui: &mut egui::Ui) {
+        for line in stats.summary() {
+            ui.label(line);
+        }
+        if stats.broken > 0 {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!("{} positions point outside of the file", stats.broken),
+            );
+        }
+
+        ui.separator();
+        ui.label("fan-out per level, positions in use: nodes");
+        let bar = ui.visuals().selection.bg_fill;
+        for (i, level) in stats.levels.iter().enumerate() {
+            let most = level.fanout.iter().copied().max().unwrap_or(1).max(1);
+            ui.horizontal_top(|ui| {
+                ui.label(format!(
+                    "level {} (under {} chars): {} nodes",
+                    i + 1,
+                    self.cache as usize + i,
+                    level.nodes
+                ));
+                ui.vertical(|ui| {
+                    for (n, count) in level.fanout.iter().enumerate() {
+                        if *count == 0 {
+                            continue;
+                        }
+                        ui.horizontal(|ui| {
+                            ui.monospace(format!("{n:>3}: {count:>8}"));
+                            let w = 200.0 * *count as f32 / most as f32;
+                            let (rect, _) = ui.allocate_exact_size(
+                                egui::vec2(w.max(1.0), 8.0),
+                                egui::Sense::hover(),
+                            );
+                            ui.painter().rect_filled(rect, 0.0, bar);
+                        });
+                    }
+                });
+            });
+        }
+
+        ui.separator();
+        ui.label(format!(
+            "cache occupancy by prefix | slots per cell: {}",
+            stats.cell
+        ));
+        self.show_heatmap(stats, ui);
+    }
+
+    /// one cell per group of slots, brighter the more of them are used.
+    /// a click lists the first slot of the cell
+    fn show_heatmap(&mut self, stats: &TrieStats, ui: &mut egui::Ui) {
+        const COLUMNS: usize = 64;
+        const SIZE: f32 = 10.0;
+        let rows = stats.heat.len().div_ceil(COLUMNS);
+        let (rect, res) = ui.allocate_exact_size(
+            egui::vec2(COLUMNS as f32 * SIZE, rows as f32 * SIZE),
+            egui::Sense::click(),
+        );
+        let empty = ui.visuals().extreme_bg_color;
+        let full = egui::Color32::GOLD;
+        let painter = ui.painter_at(rect);
+        for (i, used) in stats.heat.iter().enumerate() {
+            let start = i as u64 * stats.cell;
+            let slots = stats.cell.min(stats.slots - start);
+            let t = *used as f32 / slots as f32;
+            let color = if *used == 0 {
+                empty
+            } else {
+                empty.lerp_to_gamma(full, 0.2 + 0.8 * t)
+            };
+            let min = rect.min
+                + egui::vec2((i % COLUMNS) as f32, (i / COLUMNS) as f32) * SIZE;
+            let cell = egui::Rect::from_min_size(min, egui::vec2(SIZE, SIZE));
+            painter.rect_filled(cell.shrink(0.5), 0.0, color);
+        }
+
+        let Some(pointer) = res.hover_pos() else { return };
+        let at = (pointer - rect.min) / SIZE;
+        let i = at.y as usize * COLUMNS + at.x as usize;
+        let Some(used) = stats.heat.get(i) else { return };
+        let start = i as u64 * stats.cell;
+        let end = (start + stats.cell).min(stats.slots) - 1;
+        let text = if start == end {
+            format!("{:?}: {used}/1 used", self.trie.slot_prefix(start))
+        } else {
+            format!(
+                "{:?}..{:?}: {used}/{} used",
+                self.trie.slot_prefix(start),
+                self.trie.slot_prefix(end),
+                end - start + 1
+            )
+        };
+        if res.clicked() {
+            *self.cache_skip = start;
+        }
+        res.on_hover_text(text);
+    }
+
+    fn show_lookup(&mut self, ui: &mut egui::Ui) {
+        if let Some(res) = self.prefix_worker.poll() {
+            self.prefix = Some(res);
+        }
+        if let Some(res) = self.lookup_worker.poll() {
+            self.lookup = Some(res);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("key:");
+            let hint = self.trie.abc.iter().cycle().take(self.trie.depth());
+            let res = ui.add(
+                egui::TextEdit::singleline(&mut self.key_query)
+                    .hint_text(hint.collect::<String>())
+                    .desired_width(160.0),
+            );
+            let enter = res.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("find").clicked() || enter {
+                self.lookup_worker.request(self.key_query.clone(), ui.ctx());
+            }
+            if ui
+                .button("list prefix")
+                .on_hover_text("every key that starts with this")
+                .clicked()
+            {
+                self.prefix_worker.request(self.key_query.clone(), ui.ctx());
+            }
+            if self.prefix_worker.busy() || self.lookup_worker.busy() {
+                ui.spinner();
+            }
+        });
+
+        match self.lookup.clone() {
+            Some(Ok(lookup)) => self.show_hops(&lookup, ui),
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            None => {}
+        }
+
+        let scan = match &self.prefix {
+            Some(Ok(scan)) => scan,
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                return;
+            }
+            None => return,
+        };
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} keys under {:?}",
+                scan.keys.len(),
+                scan.prefix
+            ));
+            if scan.more {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("only the first {PREFIX_LIMIT} are listed"),
+                );
+            }
+        });
+        let height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .id_salt("prefix keys")
+            .max_height(height * 12.0)
+            .show_rows(ui, height, scan.keys.len(), |ui, range| {
+                for (key, gene) in &scan.keys[range] {
+                    ui.horizontal(|ui| {
+                        ui.monospace(key);
+                        nav::gene_link(gene, ui);
+                    });
+                }
+            });
+    }
+
+    fn show_hops(&mut self, lookup: &Lookup, ui: &mut egui::Ui) {
+        let next = |ui: &mut egui::Ui, pos: u64| {
+            if pos == 0 {
+                ui.label("---");
+            } else {
+                ui.label(pos.to_string());
+            }
+        };
+        // hop i past the cache is the node under the first cache + i - 1
+        // chars of the key
+        let cache = self.cache as usize;
+        let under = |i: usize| -> String {
+            lookup.key.chars().take(cache + i - 1).collect()
+        };
+        for (i, hop) in lookup.hops.iter().enumerate() {
+            ui.horizontal(|ui| match hop {
+                Hop::Cache { slot, next: pos } => {
+                    ui.label(format!("cache slot {slot} ->"));
+                    next(ui, *pos);
+                }
+                Hop::Node { pos, char, next: to } => {
+                    if ui.button(format!("node {pos}")).clicked() {
+                        self.open_prefix(&under(i));
+                    }
+                    ui.label(format!("[{char}] ->"));
+                    next(ui, *to);
+                }
+                Hop::Leaf { pos, char, gene } => {
+                    if ui.button(format!("leaf {pos}")).clicked() {
+                        self.open_prefix(&under(i));
+                    }
+                    ui.label(format!("[{char}] ->"));
+                    if gene.is_none() {
+                        ui.label("---");
+                    } else {
+                        nav::gene_link(gene, ui);
+                    }
+                }
+            });
+        }
+        if lookup.gene().is_none() {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("{:?} is not in the trie", lookup.key),
+            );
+        }
+    }
+
+    fn show_cache(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+
+        for (slot, prefix, p) in self.cached_cache_ui.clone() {
+            ui.horizontal(|ui| {
+                ui.monospace(&prefix)
+                    .on_hover_text(format!("cache slot {slot}"));
+                ui.label(egui::RichText::new(":").color(egui::Color32::GOLD));
+                if p == 0 {
+                    ui.label("---");
+                } else if ui.button(p.to_string()).clicked() {
+                    self.open_path(vec![(prefix, p)]);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, snapshot};
+    use shah::db::trie_const::{TRIE_CONST_MAGIC, TRIE_CONST_VERSION};
+    use std::path::Path;
+
+    #[test]
+    fn show() {
+        let mut db = TrieConstDb::init(fixtures::get().trie.clone()).unwrap();
+        let text = snapshot::render(|ui| db.show(ui));
+        snapshot::check("trie_const_show", &text);
+    }
+
+    /// writes the head of a trie const with `cache` chars in its cache and
+    /// nothing after it into `dir`
+    fn head_only(dir: &Path, name: &str, abc: &str, cache: u64) -> PathBuf {
+        let mut meta = TrieConstMeta::default();
+        meta.db.init(TRIE_CONST_MAGIC, 0, name, TRIE_CONST_VERSION);
+        meta.index = 2;
+        meta.cache = cache;
+        meta.abc_len = abc.len() as u64;
+        meta.abc[..abc.len()].copy_from_slice(abc.as_bytes());
+        let path = dir.join(format!("{name}.shah"));
+        std::fs::write(&path, meta.as_binary()).unwrap();
+        path
+    }
+
+    #[test]
+    fn large_cache() {
+        let abc = "abcdefghijklmnopqrstuvwxyz0123456789";
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        // 36^8 slots are 22 TB of cache
+        let large = head_only(dir, "large", abc, 8);
+        let mut db = TrieConstDb::init(large).unwrap();
+        assert_eq!(db.cache_len, 36u64.pow(8));
+        *db.cache_show = u64::MAX;
+        let text = snapshot::render(|ui| db.show(ui));
+        assert!(db.cache_data.capacity() <= CACHE_PAGE as usize);
+        assert!(text.contains("could not read cache at 0"), "{text}");
+
+        // the slot count does not fit in a position
+        assert!(TrieConstDb::init(head_only(dir, "huge", abc, 13)).is_err());
+    }
+
+    #[test]
+    fn show_lookup() {
+        let mut db = TrieConstDb::init(fixtures::get().trie.clone()).unwrap();
+        db.key_query = "dc".to_string();
+        db.lookup = Some(db.trie.lookup(&db.file, "dcba"));
+        db.jump_query = "dcb".to_string();
+        db.jump();
+        let mut sent = false;
+        let text = snapshot::render(|ui| {
+            if !std::mem::replace(&mut sent, true) {
+                db.prefix_worker.request(db.key_query.clone(), ui.ctx());
+            }
+            db.show(ui)
+        });
+        snapshot::check("trie_const_lookup", &text);
+    }
+
+    #[test]
+    fn show_stats() {
+        let mut db = TrieConstDb::init(fixtures::get().trie.clone()).unwrap();
+        let text = snapshot::render(|ui| {
+            if db.stats.is_none() {
+                let file = db.file.try_clone().unwrap();
+                let ctx = ui.ctx().clone();
+                db.stats = Some(StatsScan::start(db.trie.clone(), file, ctx));
+            }
+            db.show(ui)
+        });
+        snapshot::check("trie_const_stats", &text);
+    }
+}
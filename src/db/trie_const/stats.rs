@@ -0,0 +1,258 @@
+//! how full a trie const file is. every cache slot and every node under it
+//! is read once on a background thread
+
+use super::walk::{Trie, SLOT_BATCH};
+use shah::db::trie_const::TrieConstMeta;
+use shah::models::{Binary, Gene};
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+/// the heatmap has at most this many cells, slots are grouped into them
+pub const HEAT_CELLS: u64 = 1024;
+
+/// the nodes of one index level
+#[derive(Debug, Clone, Default)]
+pub struct Level {
+    pub nodes: u64,
+    /// `fanout[n]` is how many nodes have n positions in use
+    pub fanout: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TrieStats {
+    pub slots: u64,
+    /// slots that point to a node
+    pub occupied: u64,
+    /// one per index level, the last one holds the genes
+    pub levels: Vec<Level>,
+    /// keys with a gene
+    pub keys: u64,
+    pub file_size: u64,
+    /// bytes of the nodes that can be reached from the cache
+    pub node_bytes: u64,
+    /// bytes of unused positions in those nodes
+    pub empty_bytes: u64,
+    /// positions that point outside of the file
+    pub broken: u64,
+    /// slots per heatmap cell
+    pub cell: u64,
+    /// occupied slots of every cell
+    pub heat: Vec<u64>,
+}
+
+impl TrieStats {
+    /// the cache, index and space summary, one line each. the view and
+    /// the `stats` command both show these
+    pub fn summary(&self) -> [String; 3] {
+        let percent = |n: u64, of: u64| {
+            if of == 0 {
+                0.0
+            } else {
+                n as f64 * 100.0 / of as f64
+            }
+        };
+        let nodes = self.levels.iter().map(|l| l.nodes).sum::<u64>();
+        let leaves = self.levels.last().map(|l| l.nodes).unwrap_or_default();
+        [
+            format!(
+                "cache: {}/{} slots used ({:.1}%) | {} empty",
+                self.occupied,
+                self.slots,
+                percent(self.occupied, self.slots),
+                self.slots - self.occupied
+            ),
+            format!(
+                "index: {nodes} nodes | {leaves} leaves | {} keys",
+                self.keys
+            ),
+            format!(
+                "space: {} bytes | nodes {} | unused positions {} ({:.1}%) | \
+                 unreachable {}",
+                self.file_size,
+                self.node_bytes,
+                self.empty_bytes,
+                percent(self.empty_bytes, self.node_bytes),
+                self.unreachable()
+            ),
+        ]
+    }
+
+    /// bytes after the cache that no slot leads to
+    pub fn unreachable(&self) -> u64 {
+        let used = TrieConstMeta::N + self.slots * 8 + self.node_bytes;
+        self.file_size.saturating_sub(used)
+    }
+}
+
+enum StatsMsg {
+    /// slots scanned so far
+    Progress(u64),
+    Done(Result<TrieStats, String>),
+}
+
+pub struct StatsScan {
+    pub scanned: u64,
+    pub total: u64,
+    pub result: Option<Result<TrieStats, String>>,
+    rx: Receiver<StatsMsg>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl StatsScan {
+    pub fn start(trie: Trie, file: File, ctx: egui::Context) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stop = cancel.clone();
+        let total = trie.slots();
+
+        std::thread::spawn(move || {
+            let progress = |n| {
+                let _ = tx.send(StatsMsg::Progress(n));
+                ctx.request_repaint();
+            };
+            let res = scan(&trie, &file, &stop, progress);
+            if !stop.load(Ordering::Relaxed) {
+                let _ = tx.send(StatsMsg::Done(res));
+                ctx.request_repaint();
+            }
+        });
+
+        Self { scanned: 0, total, result: None, rx, cancel }
+    }
+
+    pub fn poll(&mut self) {
+        loop {
+            match self.rx.try_recv() {
+                Ok(StatsMsg::Progress(n)) => self.scanned = n,
+                Ok(StatsMsg::Done(res)) => {
+                    self.scanned = self.total;
+                    self.result = Some(res);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if self.result.is_none() {
+                        self.result = Some(Err("the scan stopped".into()));
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.result.is_some()
+    }
+}
+
+impl Drop for StatsScan {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+pub fn scan(
+    trie: &Trie, file: &File, stop: &AtomicBool, progress: impl Fn(u64),
+) -> Result<TrieStats, String> {
+    let slots = trie.slots();
+    let cell = slots.div_ceil(HEAT_CELLS).max(1);
+    let mut stats = TrieStats {
+        slots,
+        levels: vec![
+            Level { nodes: 0, fanout: vec![0; trie.abc.len() + 1] };
+            trie.index as usize
+        ],
+        file_size: file.metadata().map_err(|e| e.to_string())?.len(),
+        cell,
+        heat: vec![0; slots.div_ceil(cell) as usize],
+        ..Default::default()
+    };
+
+    let mut buf = vec![0u8; (SLOT_BATCH * 8) as usize];
+    let mut slot = 0;
+    while slot < slots {
+        if stop.load(Ordering::Relaxed) {
+            return Err("canceled".into());
+        }
+        let n = SLOT_BATCH.min(slots - slot);
+        let buf = &mut buf[..(n * 8) as usize];
+        file.read_exact_at(buf, TrieConstMeta::N + slot * 8)
+            .map_err(|e| format!("could not read cache at {slot}: {e}"))?;
+        for (i, c) in buf.chunks_exact(8).enumerate() {
+            let pos = u64::from_le_bytes(c.try_into().unwrap());
+            if pos == 0 {
+                continue;
+            }
+            let s = slot + i as u64;
+            stats.occupied += 1;
+            stats.heat[(s / cell) as usize] += 1;
+            node(trie, file, pos, 0, &mut stats);
+        }
+        slot += n;
+        progress(slot);
+    }
+    Ok(stats)
+}
+
+/// counts the node at `pos` on index `level` and every node under it
+fn node(
+    trie: &Trie, file: &File, pos: u64, level: usize, stats: &mut TrieStats,
+) {
+    let abc = trie.abc.len() as u64;
+    let leaf = level + 1 == trie.index as usize;
+    let (used, size) = if leaf {
+        let Ok(genes) = trie.read_leaf(file, pos) else {
+            stats.broken += 1;
+            return;
+        };
+        let used = genes.iter().filter(|g| g.is_some()).count() as u64;
+        stats.keys += used;
+        (used, Gene::S as u64)
+    } else {
+        let Ok(list) = trie.read_node(file, pos) else {
+            stats.broken += 1;
+            return;
+        };
+        let mut used = 0;
+        for next in list.into_iter().filter(|p| *p != 0) {
+            used += 1;
+            node(trie, file, next, level + 1, stats);
+        }
+        (used, u64::S as u64)
+    };
+    let l = &mut stats.levels[level];
+    l.nodes += 1;
+    l.fanout[used as usize] += 1;
+    stats.node_bytes += abc * size;
+    stats.empty_bytes += (abc - used) * size;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, NAMES};
+
+    #[test]
+    fn fixture() {
+        let file = File::open(&fixtures::get().trie).unwrap();
+        let trie = Trie { abc: "abcd".chars().collect(), cache: 2, index: 2 };
+        let stop = AtomicBool::new(false);
+        let stats = scan(&trie, &file, &stop, |_| {}).unwrap();
+
+        // "ab", "dc" and "dd"
+        assert_eq!((stats.slots, stats.occupied), (16, 3));
+        assert_eq!(stats.keys, NAMES.len() as u64);
+        assert_eq!(stats.levels[0].nodes, 3);
+        // "ab" has "abb" and "abc" under it
+        assert_eq!(stats.levels[0].fanout[..3], [0, 2, 1]);
+        assert_eq!(stats.levels[1].nodes, 4);
+        assert_eq!(stats.levels[1].fanout[1], 4);
+        assert_eq!(stats.broken, 0);
+        assert_eq!(stats.node_bytes, 3 * 4 * 8 + 4 * 4 * 16);
+        assert_eq!(stats.empty_bytes, (12 - 4) * 8 + (16 - 4) * 16);
+        assert_eq!(stats.heat.iter().sum::<u64>(), 3);
+        assert_eq!(stats.unreachable(), 0);
+    }
+}
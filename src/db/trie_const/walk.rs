@@ -0,0 +1,355 @@
+//! reads keys out of a trie const file the way shah looks them up. the
+//! first `cache` chars of a key pick a cache slot, every other char picks
+//! one position in an index node. the node of the last char holds the
+//! genes instead of positions
+
+use shah::db::trie_const::TrieConstMeta;
+use shah::models::{Binary, Gene};
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+
+/// keys past this are not listed by a prefix scan
+pub const PREFIX_LIMIT: usize = 10_000;
+/// cache slots read from disk at once
+pub const SLOT_BATCH: u64 = 4096;
+
+/// the layout of a trie const file
+#[derive(Debug, Clone)]
+pub struct Trie {
+    pub abc: Vec<char>,
+    pub cache: u64,
+    pub index: u64,
+}
+
+/// one read of a lookup
+#[derive(Debug, Clone)]
+pub enum Hop {
+    /// the cache slot and the node position stored in it
+    Cache { slot: u64, next: u64 },
+    /// the index node at `pos` and the position stored for `char`
+    Node { pos: u64, char: char, next: u64 },
+    /// the last node at `pos` and the gene stored for `char`
+    Leaf { pos: u64, char: char, gene: Gene },
+}
+
+#[derive(Debug, Clone)]
+pub struct Lookup {
+    pub key: String,
+    pub hops: Vec<Hop>,
+}
+
+impl Lookup {
+    /// the gene of the key, None if the walk stopped at an empty position
+    pub fn gene(&self) -> Option<Gene> {
+        match self.hops.last() {
+            Some(Hop::Leaf { gene, .. }) if gene.is_some() => Some(*gene),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PrefixScan {
+    pub prefix: String,
+    pub keys: Vec<(String, Gene)>,
+    /// true if the scan stopped at PREFIX_LIMIT
+    pub more: bool,
+}
+
+impl Trie {
+    /// chars in a full key
+    pub fn depth(&self) -> usize {
+        (self.cache + self.index) as usize
+    }
+
+    /// slots in the cache
+    pub fn slots(&self) -> u64 {
+        (self.abc.len() as u64).pow(self.cache as u32)
+    }
+
+    /// the position of every char of `key` in the abc
+    pub fn indices(&self, key: &str) -> Result<Vec<usize>, String> {
+        key.chars()
+            .map(|c| {
+                self.abc
+                    .iter()
+                    .position(|a| *a == c)
+                    .ok_or_else(|| format!("{c:?} is not in the abc"))
+            })
+            .collect()
+    }
+
+    /// the cache slot of the first `cache` chars of a key
+    pub fn slot(&self, indices: &[usize]) -> u64 {
+        let len = self.abc.len() as u64;
+        indices[..self.cache as usize]
+            .iter()
+            .fold(0, |slot, x| slot * len + *x as u64)
+    }
+
+    fn read_pos(file: &File, pos: u64) -> Result<u64, String> {
+        let mut v = 0u64;
+        file.read_exact_at(v.as_binary_mut(), pos)
+            .map_err(|e| format!("could not read at {pos}: {e}"))?;
+        Ok(v)
+    }
+
+    /// the prefix of the keys in cache slot `slot`
+    pub fn slot_prefix(&self, slot: u64) -> String {
+        let len = self.abc.len() as u64;
+        let mut rest = slot;
+        let mut chars = vec![' '; self.cache as usize];
+        for c in chars.iter_mut().rev() {
+            *c = self.abc[(rest % len) as usize];
+            rest /= len;
+        }
+        chars.into_iter().collect()
+    }
+
+    /// true if the node under a prefix of `len` chars holds genes
+    pub fn is_leaf(&self, len: usize) -> bool {
+        len + 1 == self.depth()
+    }
+
+    pub fn read_node(&self, file: &File, pos: u64) -> Result<Vec<u64>, String> {
+        let mut buf = vec![0u8; self.abc.len() * u64::S];
+        file.read_exact_at(&mut buf, pos)
+            .map_err(|e| format!("could not read the node at {pos}: {e}"))?;
+        Ok(buf
+            .chunks_exact(u64::S)
+            .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+            .collect())
+    }
+
+    pub fn read_leaf(
+        &self, file: &File, pos: u64,
+    ) -> Result<Vec<Gene>, String> {
+        let mut buf = vec![0u8; self.abc.len() * Gene::S];
+        file.read_exact_at(&mut buf, pos)
+            .map_err(|e| format!("could not read the leaf at {pos}: {e}"))?;
+        Ok(buf.chunks_exact(Gene::S).map(|c| *Gene::from_binary(c)).collect())
+    }
+
+    /// walks `key` from its cache slot to its gene. the walk stops at the
+    /// first empty position
+    pub fn lookup(&self, file: &File, key: &str) -> Result<Lookup, String> {
+        let indices = self.indices(key)?;
+        if indices.len() != self.depth() {
+            return Err(format!(
+                "a key has {} chars, {key:?} has {}",
+                self.depth(),
+                indices.len()
+            ));
+        }
+        let chars = key.chars().collect::<Vec<_>>();
+
+        let slot = self.slot(&indices);
+        let mut pos = Self::read_pos(file, TrieConstMeta::N + slot * 8)?;
+        let mut hops = vec![Hop::Cache { slot, next: pos }];
+        let cache = self.cache as usize;
+        for (i, ki) in indices.iter().enumerate().skip(cache) {
+            if pos == 0 {
+                break;
+            }
+            let char = chars[i];
+            if i + 1 == indices.len() {
+                let gene = self.read_leaf(file, pos)?[*ki];
+                hops.push(Hop::Leaf { pos, char, gene });
+                break;
+            }
+            let next = self.read_node(file, pos)?[*ki];
+            hops.push(Hop::Node { pos, char, next });
+            pos = next;
+        }
+
+        Ok(Lookup { key: key.to_string(), hops })
+    }
+
+    /// the nodes on the way to the node under `prefix` as (prefix, pos),
+    /// from the cache slot down. it stops at the first empty position
+    pub fn path(
+        &self, file: &File, prefix: &str,
+    ) -> Result<Vec<(String, u64)>, String> {
+        let indices = self.indices(prefix)?;
+        let cache = self.cache as usize;
+        if indices.len() < cache || indices.len() >= self.depth() {
+            return Err(format!(
+                "a node is under {cache} to {} chars",
+                self.depth() - 1
+            ));
+        }
+        let chars = prefix.chars().collect::<Vec<_>>();
+
+        let slot = self.slot(&indices);
+        let mut pos = Self::read_pos(file, TrieConstMeta::N + slot * 8)?;
+        let mut key = chars[..cache].iter().collect::<String>();
+        let mut path = Vec::new();
+        for i in cache..=indices.len() {
+            if pos == 0 {
+                break;
+            }
+            path.push((key.clone(), pos));
+            if i == indices.len() {
+                break;
+            }
+            pos = self.read_node(file, pos)?[indices[i]];
+            key.push(chars[i]);
+        }
+        Ok(path)
+    }
+
+    /// every key that starts with `prefix` and has a gene, in abc order
+    pub fn prefix(
+        &self, file: &File, prefix: &str,
+    ) -> Result<PrefixScan, String> {
+        let fixed = self.indices(prefix)?;
+        if fixed.len() > self.depth() {
+            return Err(format!("a key has only {} chars", self.depth()));
+        }
+        let mut scan = PrefixScan {
+            prefix: prefix.to_string(),
+            keys: Vec::new(),
+            more: false,
+        };
+
+        // the fixed chars are the high digits of the slot, so the slots
+        // under the prefix are one range
+        let cache = self.cache as usize;
+        let n = fixed.len().min(cache);
+        let mut first = fixed[..n].to_vec();
+        first.resize(cache, 0);
+        let start = self.slot(&first);
+        let span = (self.abc.len() as u64).pow((cache - n) as u32);
+        let mut key = Vec::with_capacity(self.depth());
+        let mut buf = vec![0u8; (SLOT_BATCH.min(span) * 8) as usize];
+        let mut slot = start;
+        while slot < start + span && !scan.more {
+            let n = SLOT_BATCH.min(start + span - slot);
+            let buf = &mut buf[..(n * 8) as usize];
+            file.read_exact_at(buf, TrieConstMeta::N + slot * 8)
+                .map_err(|e| format!("could not read cache at {slot}: {e}"))?;
+            for (i, c) in buf.chunks_exact(8).enumerate() {
+                let pos = u64::from_le_bytes(c.try_into().unwrap());
+                if pos == 0 {
+                    continue;
+                }
+                key.clear();
+                key.extend(self.slot_prefix(slot + i as u64).chars());
+                self.walk(file, pos, &fixed, &mut key, &mut scan)?;
+                if scan.more {
+                    break;
+                }
+            }
+            slot += n;
+        }
+        Ok(scan)
+    }
+
+    /// lists the genes under the node at `pos`, `key` has the chars of
+    /// the levels above it
+    fn walk(
+        &self, file: &File, pos: u64, fixed: &[usize], key: &mut Vec<char>,
+        scan: &mut PrefixScan,
+    ) -> Result<(), String> {
+        let level = key.len();
+        let only = fixed.get(level).copied();
+        let allowed = |ki: usize| only.is_none_or(|x| x == ki);
+
+        if self.is_leaf(level) {
+            for (ki, gene) in self.read_leaf(file, pos)?.into_iter().enumerate()
+            {
+                if !allowed(ki) || gene.is_none() {
+                    continue;
+                }
+                if scan.keys.len() >= PREFIX_LIMIT {
+                    scan.more = true;
+                    return Ok(());
+                }
+                key.push(self.abc[ki]);
+                scan.keys.push((key.iter().collect(), gene));
+                key.pop();
+            }
+            return Ok(());
+        }
+
+        for (ki, next) in self.read_node(file, pos)?.into_iter().enumerate() {
+            if !allowed(ki) || next == 0 {
+                continue;
+            }
+            key.push(self.abc[ki]);
+            self.walk(file, next, fixed, key, scan)?;
+            key.pop();
+            if scan.more {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, NAMES};
+
+    fn open() -> (Trie, File) {
+        let file = File::open(&fixtures::get().trie).unwrap();
+        let abc = "abcd".chars().collect();
+        (Trie { abc, cache: 2, index: 2 }, file)
+    }
+
+    #[test]
+    fn lookup() {
+        let (trie, file) = open();
+        for (key, id) in NAMES {
+            let lookup = trie.lookup(&file, key).unwrap();
+            assert_eq!(lookup.gene(), Some(fixtures::gene(id)), "{key}");
+            assert_eq!(lookup.hops.len(), 3);
+        }
+        let lookup = trie.lookup(&file, "abbb").unwrap();
+        assert!(matches!(lookup.hops[2], Hop::Leaf { char: 'b', .. }));
+        assert_eq!(lookup.gene(), None);
+        // slot of "ca" was never set
+        let lookup = trie.lookup(&file, "caaa").unwrap();
+        assert!(matches!(lookup.hops[..], [Hop::Cache { slot: 8, next: 0 }]));
+
+        assert!(trie.lookup(&file, "abc").is_err());
+        assert!(trie.lookup(&file, "abcx").is_err());
+    }
+
+    #[test]
+    fn prefix() {
+        let (trie, file) = open();
+        let keys = |p: &str| {
+            let scan = trie.prefix(&file, p).unwrap();
+            assert!(!scan.more);
+            scan.keys.into_iter().map(|(k, _)| k).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(""), NAMES.map(|(k, _)| k));
+        assert_eq!(keys("d"), ["dcba", "ddaa"]);
+        assert_eq!(keys("abc"), ["abcd"]);
+        assert_eq!(keys("abcd"), ["abcd"]);
+        assert!(keys("b").is_empty());
+        assert!(trie.prefix(&file, "abcda").is_err());
+    }
+
+    #[test]
+    fn path() {
+        let (trie, file) = open();
+        assert_eq!(trie.slot_prefix(0), "aa");
+        assert_eq!(trie.slot_prefix(14), "dc");
+        assert_eq!(trie.slot(&trie.indices("dc").unwrap()), 14);
+
+        let path = trie.path(&file, "dcb").unwrap();
+        let prefixes = path.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>();
+        assert_eq!(prefixes, ["dc", "dcb"]);
+        let lookup = trie.lookup(&file, "dcba").unwrap();
+        assert!(
+            matches!(lookup.hops[2], Hop::Leaf { pos, .. } if pos == path[1].1)
+        );
+        // stops at the empty position of "dca"
+        assert_eq!(trie.path(&file, "dca").unwrap().len(), 1);
+        assert!(trie.path(&file, "d").is_err());
+        assert!(trie.path(&file, "dcba").is_err());
+    }
+}
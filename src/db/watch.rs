@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// how often the watched files are checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// size and modification time of a file
+type Stamp = Option<(u64, SystemTime)>;
+
+fn stamp(path: &PathBuf) -> Stamp {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// polls the size and mtime of files on a background thread
+pub struct FileWatch {
+    rx: Receiver<()>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl FileWatch {
+    pub fn start(paths: Vec<PathBuf>, ctx: egui::Context) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stop = cancel.clone();
+
+        std::thread::spawn(move || {
+            let mut stamps = paths.iter().map(stamp).collect::<Vec<_>>();
+            loop {
+                std::thread::sleep(WATCH_INTERVAL);
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let mut changed = false;
+                for (path, old) in paths.iter().zip(stamps.iter_mut()) {
+                    let new = stamp(path);
+                    if new != *old {
+                        *old = new;
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    continue;
+                }
+                if tx.send(()).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+
+        Self { rx, cancel }
+    }
+
+    /// returns true if any of the files changed since the last poll
+    pub fn poll(&self) -> bool {
+        let mut changed = false;
+        loop {
+            match self.rx.try_recv() {
+                Ok(()) => changed = true,
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => {
+                    return changed
+                }
+            }
+        }
+    }
+}
+
+impl Drop for FileWatch {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
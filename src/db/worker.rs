@@ -0,0 +1,66 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// runs reads on a background thread. requests that queue up while a
+/// read is running are coalesced, only the newest one is run
+pub struct Worker<Req, Res> {
+    tx: Sender<(u64, Req, egui::Context)>,
+    rx: Receiver<(u64, Res)>,
+    sent: u64,
+    done: u64,
+}
+
+impl<Req: Send + 'static, Res: Send + 'static> Worker<Req, Res> {
+    pub fn start(mut job: impl FnMut(Req) -> Res + Send + 'static) -> Self {
+        let (tx, jobs) = mpsc::channel::<(u64, Req, egui::Context)>();
+        let (results, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            while let Ok(mut next) = jobs.recv() {
+                while let Ok(newer) = jobs.try_recv() {
+                    next = newer;
+                }
+                let (generation, req, ctx) = next;
+                if results.send((generation, job(req))).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+
+        Self { tx, rx, sent: 0, done: 0 }
+    }
+
+    pub fn request(&mut self, req: Req, ctx: &egui::Context) {
+        self.sent += 1;
+        if self.tx.send((self.sent, req, ctx.clone())).is_err() {
+            log::error!("worker thread is gone");
+            self.done = self.sent;
+        }
+    }
+
+    /// the response to the newest request, once it is ready
+    pub fn poll(&mut self) -> Option<Res> {
+        let mut latest = None;
+        while let Ok((generation, res)) = self.rx.try_recv() {
+            self.done = generation;
+            if generation == self.sent {
+                latest = Some(res);
+            }
+        }
+        latest
+    }
+
+    /// every response that arrived, including superseded ones
+    pub fn drain(&mut self) -> Vec<Res> {
+        let mut list = Vec::new();
+        while let Ok((generation, res)) = self.rx.try_recv() {
+            self.done = generation;
+            list.push(res);
+        }
+        list
+    }
+
+    pub fn busy(&self) -> bool {
+        self.done < self.sent
+    }
+}
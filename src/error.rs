@@ -1,9 +1,11 @@
 use shah::ShahError;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum ShahVisualError {
     Shah(ShahError),
     NotShahDb,
+    MissingFile(PathBuf),
 }
 
 // impl From<ShahError> for ShahVisualError {
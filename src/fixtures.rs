@@ -0,0 +1,220 @@
+//! small databases written with shah itself for the tests. they are made
+//! once per test process in `target/fixtures`, laid out like a real scope.
+//! the directory is cleared and written again on every run:
+//!
+//! - `shop/item/item.0.shah` entity with every primitive, a string, genes
+//!   and a nested model
+//! - `shop/cut/cut.0.shah` the same model with a truncated tail
+//! - `shop/names.shah` trie const of `abcd` keys to item genes
+//! - `shop/tags.shah` trie of `abcd` keys of any length to item genes
+
+use shah::db::entity::{EntityDb, ENTITY_META};
+use shah::db::trie::{Trie, TrieAbc, TrieKey};
+use shah::db::trie_const::{TrieConst, TrieConstAbc};
+use shah::models::{Binary, DbHead, Gene, GeneId};
+use shah::ShahError;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[derive(shah::ShahSchema)]
+#[shah::model]
+#[derive(Debug)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(shah::ShahSchema)]
+#[shah::model]
+#[derive(Debug, shah::Entity)]
+pub struct Item {
+    pub gene: Gene,
+    pub owner: Gene,
+    pub friends: [Gene; 2],
+    #[str]
+    pub name: [u8; 12],
+    pub small: u8,
+    pub active: bool,
+    pub delta: i16,
+    pub age: u32,
+    pub score: f32,
+    pub big: i64,
+    pub ratio: f64,
+    pub pos: Point,
+    growth: u64,
+    entity_flags: u32,
+    _pad: u32,
+}
+
+/// rows of the entity fixtures, item 0 is the empty one shah reserves
+pub const ITEMS: u64 = 5;
+/// bytes after the last item of the cut fixture
+pub const CUT_TAIL: usize = 7;
+/// the trie const keys and the item ids they point to
+pub const NAMES: [(&str, u64); 4] =
+    [("abba", 1), ("abcd", 2), ("dcba", 3), ("ddaa", 4)];
+/// the trie keys and the item ids they point to, in abc order
+pub const TAGS: [(&str, u64); 5] =
+    [("a", 1), ("ab", 2), ("abc", 3), ("ca", 4), ("dd", 1)];
+
+pub struct Abc;
+
+impl TrieConstAbc<4> for Abc {
+    type Item<'a> = &'a str;
+    const ABC: &'static str = "abcd";
+
+    fn convert(&self, key: &str) -> Result<[usize; 4], ShahError> {
+        let mut out = [0; 4];
+        for (o, c) in out.iter_mut().zip(key.bytes()) {
+            *o = (c - b'a') as usize;
+        }
+        Ok(out)
+    }
+}
+
+/// the first char of a key picks the root position, every other one a
+/// node under it
+pub struct Tags;
+
+impl TrieAbc for Tags {
+    type Item<'a> = &'a str;
+    const ABC: &'static str = "abcd";
+
+    fn convert(&self, key: &str) -> Result<TrieKey, ShahError> {
+        let mut out = TrieKey::new(key.len());
+        for (i, c) in key.bytes().enumerate() {
+            let x = (c - b'a') as usize;
+            if i == 0 {
+                out.root = x;
+            } else {
+                out.tree.push(x);
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// the gene of item `id`. shah peppers genes at random, the fixtures pin
+/// them so rendered output is the same in every run
+pub fn gene(id: u64) -> Gene {
+    if id == 0 {
+        return Gene::default();
+    }
+    let p = id as u8;
+    Gene { id: GeneId(id), iter: 0, pepper: [p, p * 2, p * 3], server: 1 }
+}
+
+fn item(id: u64) -> Item {
+    let mut name = [0; 12];
+    let s = ["", "apple", "bread", "cheese", "dates\u{e9}"][id as usize];
+    name[..s.len()].copy_from_slice(s.as_bytes());
+    Item {
+        owner: gene(id / 2),
+        friends: [gene(id - 1), Gene { iter: 1, ..gene(1) }],
+        name,
+        small: id as u8 * 50,
+        active: id % 2 == 1,
+        delta: -(id as i16) * 100,
+        age: id as u32 * 10,
+        score: id as f32 / 4.0,
+        big: i64::MIN + id as i64,
+        ratio: 1.0 / id as f64,
+        pos: Point { x: id as i32, y: -(id as i32) },
+        ..Default::default()
+    }
+}
+
+/// writes the items with shah and pins their genes. returns the file
+fn entity(data: &Path, db: &str) -> PathBuf {
+    let dir = data.join(format!("shop/{db}"));
+    let mut edb = EntityDb::<Item>::new(dir.to_str().unwrap(), 0).unwrap();
+    for id in 1..ITEMS {
+        edb.add(&mut item(id)).unwrap();
+    }
+    drop(edb);
+
+    let path = dir.join(format!("{db}.0.shah"));
+    let file = OpenOptions::new().write(true).open(&path).unwrap();
+    for id in 1..ITEMS {
+        let pos = ENTITY_META + id * size_of::<Item>() as u64;
+        file.write_all_at(gene(id).as_binary(), pos).unwrap();
+    }
+    path
+}
+
+/// shah names a trie after the path it is made with. the fixtures are
+/// made with absolute paths, so the head gets the name a scope gives it
+fn rename(path: &Path, name: &str) {
+    let file = OpenOptions::new().read(true).write(true).open(path).unwrap();
+    let mut head = DbHead::default();
+    file.read_exact_at(head.as_binary_mut(), 0).unwrap();
+    let head = DbHead::new(head.magic, head.revision, name, head.db_version);
+    file.write_all_at(head.as_binary(), 0).unwrap();
+}
+
+fn trie(data: &Path) -> PathBuf {
+    let name = data.join("shop/names");
+    let mut db =
+        TrieConst::<4, 2, 2, 4, Abc, Gene>::new(name.to_str().unwrap(), Abc)
+            .unwrap();
+    for (key, id) in NAMES {
+        let key = db.key(key).unwrap();
+        db.set(&key, gene(id)).unwrap();
+    }
+    let path = data.join("shop/names.shah");
+    rename(&path, "shop/names");
+    path
+}
+
+fn tags(data: &Path) -> PathBuf {
+    let name = data.join("shop/tags");
+    let mut db =
+        Trie::<4, Tags, Gene>::new(name.to_str().unwrap(), Tags).unwrap();
+    for (key, id) in TAGS {
+        let key = db.key(key).unwrap();
+        db.set(&key, gene(id)).unwrap();
+    }
+    let path = data.join("shop/tags.shah");
+    rename(&path, "shop/tags");
+    path
+}
+
+pub struct Fixtures {
+    /// the `data` directory, its parent is the scope
+    pub data: PathBuf,
+    pub item: PathBuf,
+    pub cut: PathBuf,
+    pub trie: PathBuf,
+    pub tags: PathBuf,
+}
+
+static FIXTURES: OnceLock<Fixtures> = OnceLock::new();
+
+/// the fixture databases, written on first use
+pub fn get() -> &'static Fixtures {
+    FIXTURES.get_or_init(|| {
+        let root =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("target/fixtures");
+        if root.exists() {
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+        let data = root.join("fixture/data");
+        std::fs::create_dir_all(&data).unwrap();
+        // shah reads its config from the env on every db it opens. the
+        // paths given to it are absolute, these only keep it from
+        // panicking or making a `data` directory in the crate
+        std::env::set_var("SHAH_SERVER_INDEX", "1");
+        std::env::set_var("SHAH_DATA_DIR", root.join("shah-data"));
+
+        let item = entity(&data, "item");
+        let cut = entity(&data, "cut");
+        let mut file = OpenOptions::new().append(true).open(&cut).unwrap();
+        file.write_all(&[0xff; CUT_TAIL]).unwrap();
+        let trie = trie(&data);
+        let tags = tags(&data);
+        Fixtures { data, item, cut, trie, tags }
+    })
+}
@@ -1,11 +1,19 @@
 mod app;
+mod check;
+mod cli;
 mod config;
 mod db;
 mod error;
+#[cfg(test)]
+mod fixtures;
 mod fonts;
 mod shortcuts;
+#[cfg(test)]
+mod snapshot;
 mod tiles;
 mod utils;
+mod value;
+mod workspace;
 
 pub use error::Result;
 
@@ -13,6 +21,12 @@ fn main() {
     unsafe { std::env::set_var("RUST_LOG", "info") };
     pretty_env_logger::init();
 
+    let cli = <cli::Cli as clap::Parser>::parse();
+    if let Some(command) = cli.command {
+        std::process::exit(command.run());
+    }
+
+    let (workspace, paths) = (cli.workspace, cli.paths);
     let native_options = eframe::NativeOptions {
         persistence_path: Some("./pref.json".into()),
         ..Default::default()
@@ -21,7 +35,9 @@ fn main() {
     eframe::run_native(
         "00-team-test-app",
         native_options,
-        Box::new(|cc| Ok(Box::new(app::ShahApp::new(cc).unwrap()))),
+        Box::new(|cc| {
+            Ok(Box::new(app::ShahApp::new(cc, workspace, paths).unwrap()))
+        }),
     )
     .unwrap();
 }
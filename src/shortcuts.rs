@@ -13,4 +13,5 @@ shortcuts! {
     QUIT: CTRL + Q,
     FULLSCREEN: NONE + F,
     OPEN_FILE: CTRL + O,
+    SAVE_WORKSPACE: CTRL + S,
 }
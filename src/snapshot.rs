@@ -0,0 +1,79 @@
+//! headless rendering for the ui tests. frames are drawn without a window
+//! and the text they paint is compared to `tests/snapshots/<name>.txt`.
+//! run the tests with `UPDATE_SNAPSHOTS=1` to write the snapshots again
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// frames in a row that have to paint the same text before it is taken,
+/// background reads finish in between
+const SETTLE: usize = 5;
+const MAX_FRAMES: usize = 200;
+
+fn collect(shape: &egui::Shape, out: &mut String) {
+    match shape {
+        egui::Shape::Vec(list) => list.iter().for_each(|s| collect(s, out)),
+        egui::Shape::Text(t) if !t.galley.text().trim().is_empty() => {
+            let text = t.galley.text().replace('\n', "\\n");
+            out.push_str(&format!(
+                "{:>4.0} {:>4.0}  {text}\n",
+                t.pos.x, t.pos.y
+            ));
+        }
+        _ => {}
+    }
+}
+
+/// the text of a settled frame of `show` in a central panel, one painted
+/// text per line with its position
+pub fn render(mut show: impl FnMut(&mut egui::Ui)) -> String {
+    let ctx = egui::Context::default();
+    let rect =
+        egui::Rect::from_min_size(Default::default(), [1200., 900.].into());
+    let mut last = String::new();
+    let mut same = 0;
+    for frame in 0..MAX_FRAMES {
+        let input = egui::RawInput {
+            screen_rect: Some(rect),
+            time: Some(frame as f64 / 60.0),
+            ..Default::default()
+        };
+        let output = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| show(ui));
+        });
+        let mut text = String::new();
+        for clipped in output.shapes.iter() {
+            collect(&clipped.shape, &mut text);
+        }
+        if text == last {
+            same += 1;
+            if same == SETTLE {
+                break;
+            }
+        } else {
+            same = 0;
+            last = text;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    last
+}
+
+/// compares `text` with the snapshot `name`, or writes it when it is
+/// missing or `UPDATE_SNAPSHOTS` is set
+pub fn check(name: &str, text: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join(format!("tests/snapshots/{name}.txt"));
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    match std::fs::read_to_string(&path) {
+        Ok(old) if !update => assert!(
+            old == text,
+            "snapshot {name} changed, run with UPDATE_SNAPSHOTS=1 to accept \
+             it\n--- old\n{old}\n--- new\n{text}"
+        ),
+        _ => {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, text).unwrap();
+        }
+    }
+}
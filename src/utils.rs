@@ -1,53 +1,79 @@
 use std::path::{Component, Path};
 
-// use shah::models::Gene;
+use shah::models::Gene;
 
-// pub fn gene(gene: &Gene, name: &'static str, ui: &mut egui::Ui) -> egui::Response {
-//     ui.add_enabled(
-//         gene.is_some(),
-//         egui::Button::new(format!(
-//             "{name}({}, {}, {:?}, {})",
-//             gene.id, gene.iter, gene.pepper, gene.server
-//         )),
-//     )
-// }
-//
-// pub fn stroke(alive: bool, free: bool, visuals: &egui::Visuals) -> egui::Stroke {
-//     if !alive {
-//         return egui::Stroke::new(visuals.window_stroke.width, visuals.error_fg_color);
-//     }
-//
-//     if free {
-//         return egui::Stroke::new(visuals.window_stroke.width, visuals.warn_fg_color);
-//     }
-//
-//     visuals.window_stroke
-// }
+pub fn gene(gene: &Gene, name: &str, ui: &mut egui::Ui) -> egui::Response {
+    ui.add_enabled(
+        gene.is_some(),
+        egui::Button::new(format!(
+            "{name}({}, {}, {:?}, {})",
+            gene.id, gene.iter, gene.pepper, gene.server
+        )),
+    )
+}
+
+pub fn stroke(
+    alive: bool, free: bool, visuals: &egui::Visuals,
+) -> egui::Stroke {
+    if !alive {
+        return egui::Stroke::new(
+            visuals.window_stroke.width,
+            visuals.error_fg_color,
+        );
+    }
+
+    if free {
+        return egui::Stroke::new(
+            visuals.window_stroke.width,
+            visuals.warn_fg_color,
+        );
+    }
+
+    visuals.window_stroke
+}
+
+pub struct ColoredBool {
+    name: &'static str,
+    value: bool,
+}
+
+impl ColoredBool {
+    /// an empty name shows only the value, for table cells
+    pub fn new(name: &'static str, value: bool) -> Self {
+        Self { name, value }
+    }
+}
 
-// pub struct ColoredBool {
-//     name: &'static str,
-//     value: bool,
-// }
-//
-// impl ColoredBool {
-//     pub fn new(name: &'static str, value: bool) -> Self {
-//         Self { name, value }
-//     }
-// }
-//
-// impl egui::Widget for ColoredBool {
-//     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-//         ui.horizontal_wrapped(|ui| {
-//             ui.label(format!("{}:", self.name));
-//             if self.value {
-//                 ui.colored_label(egui::Color32::LIGHT_GREEN, "true");
-//             } else {
-//                 ui.colored_label(egui::Color32::LIGHT_RED, "false");
-//             }
-//         })
-//         .response
-//     }
-// }
+impl egui::Widget for ColoredBool {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.horizontal_wrapped(|ui| {
+            if !self.name.is_empty() {
+                ui.label(format!("{}:", self.name));
+            }
+            if self.value {
+                ui.colored_label(egui::Color32::LIGHT_GREEN, "true");
+            } else {
+                ui.colored_label(egui::Color32::LIGHT_RED, "false");
+            }
+        })
+        .response
+    }
+}
+
+pub const HEX_ROW: usize = 16;
+
+/// formats one row of a hex dump as `offset  hex bytes  ascii`
+pub fn hex_row(offset: u64, row: &[u8]) -> String {
+    let mut hex = String::with_capacity(HEX_ROW * 3);
+    for b in row {
+        hex.push_str(&format!("{b:02x} "));
+    }
+    let ascii = row
+        .iter()
+        .map(|b| if b.is_ascii_graphic() { *b as char } else { '.' })
+        .collect::<String>();
+    format!("{offset:08x}  {hex:<w$} {ascii}", w = HEX_ROW * 3)
+}
 
 pub fn db_name(path: &Path) -> (&str, &str, String) {
     let mut after_data = false;
@@ -62,10 +88,37 @@ pub fn db_name(path: &Path) -> (&str, &str, String) {
         if a == "data" {
             after_data = true;
         } else {
-            y.insert(0, x);
+            if !x.is_empty() {
+                y.insert(0, x);
+            }
             // y = format!("{x}/{y}");
             x = g;
         }
     }
     ("", x, y[..y.len().min(2)].join("/"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn db_name_in_data() {
+        let path = Path::new("/srv/app/data/shop/item/item.0.shah");
+        assert_eq!(db_name(path), ("app", "shop", "item/item.0.shah".into()));
+        let path = Path::new("/srv/app/data/shop/names.shah");
+        assert_eq!(db_name(path), ("app", "shop", "names.shah".into()));
+        // only two levels under the db are kept
+        let path = Path::new("data/shop/a/b/c.shah");
+        assert_eq!(db_name(path), ("", "shop", "a/b".into()));
+    }
+
+    #[test]
+    fn db_name_without_data() {
+        let path = Path::new("/tmp/item/item.0.shah");
+        assert_eq!(db_name(path), ("", "tmp", "item/item.0.shah".into()));
+        let path = Path::new("item.0.shah");
+        assert_eq!(db_name(path), ("", "item.0.shah", "".into()));
+        assert_eq!(db_name(Path::new("")), ("", "", "".into()));
+    }
+}
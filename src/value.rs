@@ -0,0 +1,481 @@
+//! values of a schema decoded from raw item bytes, without any gui.
+//!
+//! every value has one stable textual form so exports can be diffed:
+//!
+//! - numbers use the shortest form that reads back to the same value.
+//!   non finite floats are `NaN`, `inf` and `-inf`, or `null` in json
+//! - bools are `true` / `false`
+//! - strings are decoded up to the first null byte, an invalid utf-8
+//!   tail is dropped
+//! - genes are `Gene(id, iter, [p, e, p], server)`, the same form the
+//!   filter accepts. json has them as a string
+//! - arrays and tuples are json arrays, nested models are json objects
+//!   in schema order. as text they are the json form
+
+use shah::models::{Binary, Gene, Schema, SchemaModel};
+use std::cmp::Ordering;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    Gene(Gene),
+    Str(String),
+    Array(Vec<Decoded>),
+    Tuple(Vec<Decoded>),
+    Model { name: String, fields: Vec<(String, Decoded)> },
+}
+
+/// the valid utf-8 part of a null terminated string array
+pub fn decode_str(v: &[u8]) -> &str {
+    let sv = v.splitn(2, |x| *x == 0).next().unwrap();
+    match core::str::from_utf8(sv) {
+        Ok(v) => v,
+        Err(e) => core::str::from_utf8(&sv[..e.valid_up_to()]).unwrap(),
+    }
+}
+
+/// a copy of `schema`, the shah type is not Clone
+pub fn clone_schema(schema: &Schema) -> Schema {
+    match schema {
+        Schema::Model(m) => Schema::Model(clone_model(m)),
+        Schema::Array { is_str, length, kind } => Schema::Array {
+            is_str: *is_str,
+            length: *length,
+            kind: Box::new(clone_schema(kind)),
+        },
+        Schema::Tuple(list) => {
+            Schema::Tuple(list.iter().map(clone_schema).collect())
+        }
+        Schema::U8 => Schema::U8,
+        Schema::U16 => Schema::U16,
+        Schema::U32 => Schema::U32,
+        Schema::U64 => Schema::U64,
+        Schema::I8 => Schema::I8,
+        Schema::I16 => Schema::I16,
+        Schema::I32 => Schema::I32,
+        Schema::I64 => Schema::I64,
+        Schema::F32 => Schema::F32,
+        Schema::F64 => Schema::F64,
+        Schema::Bool => Schema::Bool,
+        Schema::Gene => Schema::Gene,
+    }
+}
+
+pub fn clone_model(model: &SchemaModel) -> SchemaModel {
+    SchemaModel {
+        name: model.name.clone(),
+        size: model.size,
+        fields: model
+            .fields
+            .iter()
+            .map(|(n, s)| (n.clone(), clone_schema(s)))
+            .collect(),
+    }
+}
+
+pub fn json_str(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+impl Decoded {
+    /// decodes `v`, which must be exactly `schema.size()` bytes
+    pub fn decode(schema: &Schema, v: &[u8]) -> Self {
+        macro_rules! num {
+            ($var:ident, $ty:ty) => {
+                Self::$var(<$ty>::from_le_bytes(v.try_into().unwrap()))
+            };
+        }
+        match schema {
+            Schema::U8 => num!(U8, u8),
+            Schema::U16 => num!(U16, u16),
+            Schema::U32 => num!(U32, u32),
+            Schema::U64 => num!(U64, u64),
+            Schema::I8 => num!(I8, i8),
+            Schema::I16 => num!(I16, i16),
+            Schema::I32 => num!(I32, i32),
+            Schema::I64 => num!(I64, i64),
+            Schema::F32 => num!(F32, f32),
+            Schema::F64 => num!(F64, f64),
+            Schema::Bool => Self::Bool(v[0] != 0),
+            Schema::Gene => {
+                let mut g = Gene::default();
+                g.as_binary_mut().copy_from_slice(v);
+                Self::Gene(g)
+            }
+            Schema::Array { is_str: true, .. } => {
+                Self::Str(decode_str(v).to_string())
+            }
+            Schema::Array { kind, .. } => {
+                let size = kind.size().max(1);
+                Self::Array(
+                    v.chunks_exact(size)
+                        .map(|c| Self::decode(kind, c))
+                        .collect(),
+                )
+            }
+            Schema::Tuple(list) => {
+                let mut pos = 0;
+                Self::Tuple(
+                    list.iter()
+                        .map(|s| {
+                            let r = pos..pos + s.size();
+                            pos = r.end;
+                            Self::decode(s, &v[r])
+                        })
+                        .collect(),
+                )
+            }
+            Schema::Model(m) => Self::decode_model(m, v),
+        }
+    }
+
+    pub fn decode_model(model: &SchemaModel, v: &[u8]) -> Self {
+        let mut pos = 0;
+        let fields = model
+            .fields
+            .iter()
+            .map(|(name, s)| {
+                let r = pos..pos + s.size();
+                pos = r.end;
+                (name.clone(), Self::decode(s, &v[r]))
+            })
+            .collect();
+        Self::Model { name: model.name.clone(), fields }
+    }
+
+    /// the number as f64, None for anything that is not a number
+    pub fn as_f64(&self) -> Option<f64> {
+        Some(match *self {
+            Self::U8(x) => x as f64,
+            Self::U16(x) => x as f64,
+            Self::U32(x) => x as f64,
+            Self::U64(x) => x as f64,
+            Self::I8(x) => x as f64,
+            Self::I16(x) => x as f64,
+            Self::I32(x) => x as f64,
+            Self::I64(x) => x as f64,
+            Self::F32(x) => x as f64,
+            Self::F64(x) => x,
+            _ => return None,
+        })
+    }
+
+    /// the integer without loss, None for floats and non numbers
+    pub fn as_i128(&self) -> Option<i128> {
+        Some(match *self {
+            Self::U8(x) => x as i128,
+            Self::U16(x) => x as i128,
+            Self::U32(x) => x as i128,
+            Self::U64(x) => x as i128,
+            Self::I8(x) => x as i128,
+            Self::I16(x) => x as i128,
+            Self::I32(x) => x as i128,
+            Self::I64(x) => x as i128,
+            _ => return None,
+        })
+    }
+
+    /// total order used for sorting. numbers are compared by value with
+    /// floats using [`f64::total_cmp`], genes by their components and
+    /// containers item by item
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        if let (Some(a), Some(b)) = (self.as_i128(), other.as_i128()) {
+            return a.cmp(&b);
+        }
+        if let (Some(a), Some(b)) = (self.as_f64(), other.as_f64()) {
+            return a.total_cmp(&b);
+        }
+        let list = |a: &[Self], b: &[Self]| {
+            for (x, y) in a.iter().zip(b.iter()) {
+                let o = x.total_cmp(y);
+                if o.is_ne() {
+                    return o;
+                }
+            }
+            a.len().cmp(&b.len())
+        };
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::Str(a), Self::Str(b)) => a.cmp(b),
+            (Self::Gene(a), Self::Gene(b)) => {
+                let a = (a.id, a.iter, a.pepper, a.server);
+                a.cmp(&(b.id, b.iter, b.pepper, b.server))
+            }
+            (Self::Array(a), Self::Array(b)) => list(a, b),
+            (Self::Tuple(a), Self::Tuple(b)) => list(a, b),
+            _ => Ordering::Equal,
+        }
+    }
+
+    /// the zero value of its type: 0, false, an empty string or gene and
+    /// containers of only zero values
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Self::Bool(b) => !b,
+            Self::Gene(g) => g.as_binary().iter().all(|x| *x == 0),
+            Self::Str(s) => s.is_empty(),
+            Self::Array(l) | Self::Tuple(l) => l.iter().all(Self::is_zero),
+            Self::Model { fields, .. } => {
+                fields.iter().all(|(_, v)| v.is_zero())
+            }
+            v => v.as_f64() == Some(0.0),
+        }
+    }
+
+    /// the text form, strings are not quoted
+    pub fn write_text(&self, out: &mut String) {
+        let _ = match self {
+            Self::U8(x) => write!(out, "{x}"),
+            Self::U16(x) => write!(out, "{x}"),
+            Self::U32(x) => write!(out, "{x}"),
+            Self::U64(x) => write!(out, "{x}"),
+            Self::I8(x) => write!(out, "{x}"),
+            Self::I16(x) => write!(out, "{x}"),
+            Self::I32(x) => write!(out, "{x}"),
+            Self::I64(x) => write!(out, "{x}"),
+            Self::F32(x) => write!(out, "{x}"),
+            Self::F64(x) => write!(out, "{x}"),
+            Self::Bool(x) => write!(out, "{x}"),
+            Self::Gene(g) => write!(
+                out,
+                "Gene({}, {}, {:?}, {})",
+                g.id, g.iter, g.pepper, g.server
+            ),
+            Self::Str(s) => {
+                out.push_str(s);
+                Ok(())
+            }
+            _ => {
+                self.write_json(out);
+                Ok(())
+            }
+        };
+    }
+
+    pub fn write_json(&self, out: &mut String) {
+        let list = |items: &[Decoded], out: &mut String| {
+            out.push('[');
+            for (i, v) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                v.write_json(out);
+            }
+            out.push(']');
+        };
+        match self {
+            Self::F32(x) if !x.is_finite() => out.push_str("null"),
+            Self::F64(x) if !x.is_finite() => out.push_str("null"),
+            Self::Gene(_) => {
+                let mut s = String::new();
+                self.write_text(&mut s);
+                json_str(&s, out);
+            }
+            Self::Str(s) => json_str(s, out),
+            Self::Array(items) | Self::Tuple(items) => list(items, out),
+            Self::Model { fields, .. } => {
+                out.push('{');
+                for (i, (name, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    json_str(name, out);
+                    out.push_str(": ");
+                    v.write_json(out);
+                }
+                out.push('}');
+            }
+            v => v.write_text(out),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out);
+        out
+    }
+
+    pub fn json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+}
+
+/// min, max and total of the numbers in a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberStats {
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub total: f64,
+}
+
+impl Default for NumberStats {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            total: 0.0,
+        }
+    }
+}
+
+impl NumberStats {
+    /// adds a value, anything that is not a number is skipped
+    pub fn add(&mut self, v: &Decoded) {
+        let Some(x) = v.as_f64() else { return };
+        self.count += 1;
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        self.total += x;
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total / self.count as f64)
+    }
+}
+
+impl std::fmt::Display for NumberStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mean() {
+            Some(avg) => write!(
+                f,
+                "min/max/avg/total: {}/{}/{avg}/{}",
+                self.min, self.max, self.total
+            ),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, Item, Point};
+    use shah::models::{GeneId, ShahSchema};
+
+    fn decode<T: Binary + ShahSchema>(v: &T) -> Decoded {
+        Decoded::decode(&T::shah_schema(), v.as_binary())
+    }
+
+    #[test]
+    fn primitives() {
+        assert_eq!(Decoded::decode(&Schema::U8, &[200]), Decoded::U8(200));
+        assert_eq!(Decoded::decode(&Schema::Bool, &[2]), Decoded::Bool(true));
+        let v = Decoded::decode(&Schema::I16, &(-300i16).to_le_bytes());
+        assert_eq!(v, Decoded::I16(-300));
+        assert_eq!(v.as_i128(), Some(-300));
+        let v = Decoded::decode(&Schema::U64, &u64::MAX.to_le_bytes());
+        assert_eq!(v.as_i128(), Some(u64::MAX as i128));
+        let v = Decoded::decode(&Schema::F32, &0.1f32.to_le_bytes());
+        assert_eq!(v.text(), "0.1");
+        assert_eq!(v.as_i128(), None);
+        let v = Decoded::decode(&Schema::F64, &f64::NAN.to_le_bytes());
+        assert_eq!((v.text().as_str(), v.json().as_str()), ("NaN", "null"));
+    }
+
+    #[test]
+    fn strings() {
+        let schema = Schema::Array {
+            is_str: true,
+            length: 8,
+            kind: Box::new(Schema::U8),
+        };
+        let v = Decoded::decode(&schema, b"ab\"c\0xyz");
+        assert_eq!(v, Decoded::Str("ab\"c".to_string()));
+        assert_eq!(v.json(), r#""ab\"c""#);
+        // the invalid tail is dropped
+        assert_eq!(decode_str(b"ok\xff\xfe\0\0"), "ok");
+        assert!(Decoded::decode(&schema, &[0; 8]).is_zero());
+    }
+
+    #[test]
+    fn genes() {
+        let g = Gene { id: GeneId(7), iter: 2, pepper: [1, 2, 3], server: 9 };
+        let v = Decoded::decode(&Schema::Gene, g.as_binary());
+        assert_eq!(v.text(), "Gene(7, 2, [1, 2, 3], 9)");
+        assert_eq!(v.json(), "\"Gene(7, 2, [1, 2, 3], 9)\"");
+        assert!(!v.is_zero());
+        assert!(Decoded::decode(&Schema::Gene, &[0; Gene::S]).is_zero());
+    }
+
+    #[test]
+    fn models() {
+        let v = decode(&Point { x: 3, y: -4 });
+        assert_eq!(v.json(), r#"{"x": 3, "y": -4}"#);
+        let Decoded::Model { name, fields } = &v else { panic!("{v:?}") };
+        assert_eq!(name, "Point");
+        assert_eq!(fields[1], ("y".to_string(), Decoded::I32(-4)));
+
+        let mut it = Item::default();
+        it.gene = fixtures::gene(2);
+        it.friends[1] = fixtures::gene(1);
+        it.name[..4].copy_from_slice(b"kiwi");
+        it.pos.x = 5;
+        let v = decode(&it);
+        let Decoded::Model { fields, .. } = &v else { panic!("{v:?}") };
+        let field = |n: &str| &fields.iter().find(|(k, _)| k == n).unwrap().1;
+        assert_eq!(field("gene"), &Decoded::Gene(fixtures::gene(2)));
+        assert_eq!(field("name"), &Decoded::Str("kiwi".to_string()));
+        assert_eq!(
+            field("friends").json(),
+            r#"["Gene(0, 0, [0, 0, 0], 0)", "Gene(1, 0, [1, 2, 3], 1)"]"#
+        );
+        assert_eq!(field("pos").text(), r#"{"x": 5, "y": 0}"#);
+        assert!(!v.is_zero());
+        assert!(decode(&Item::default()).is_zero());
+    }
+
+    #[test]
+    fn number_stats() {
+        let mut stats = NumberStats::default();
+        assert_eq!(stats.to_string(), "-");
+        for v in [Decoded::I8(-2), Decoded::Bool(true), Decoded::F64(5.0)] {
+            stats.add(&v);
+        }
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.mean(), Some(1.5));
+        assert_eq!(stats.to_string(), "min/max/avg/total: -2/5/1.5/3");
+    }
+
+    #[test]
+    fn ordering() {
+        let a = Decoded::U64(u64::MAX);
+        assert!(a.total_cmp(&Decoded::U64(1)).is_gt());
+        let nan = Decoded::F64(f64::NAN);
+        assert!(Decoded::F64(f64::INFINITY).total_cmp(&nan).is_lt());
+        let g = |id| Decoded::Gene(fixtures::gene(id));
+        assert!(g(1).total_cmp(&g(2)).is_lt());
+        let s = |v: &str| Decoded::Str(v.to_string());
+        let list =
+            |v: &[&str]| Decoded::Array(v.iter().map(|x| s(x)).collect());
+        assert!(list(&["a", "b"]).total_cmp(&list(&["a"])).is_gt());
+    }
+}
@@ -0,0 +1,396 @@
+//! a workspace is a toml file with the open databases, the tile layout,
+//! the view of every tile and the paths listed in the side panel:
+//!
+//! ```toml
+//! roots = ["/srv/app/data"]
+//!
+//! [[tiles]]
+//! path = "/srv/app/data/user/user.0.shah"
+//! filter = "age > 30"
+//! sort = ["-age"]
+//!
+//! [layout]
+//! kind = "horizontal"
+//! shares = [1.0]
+//!
+//! [[layout.children]]
+//! kind = "pane"
+//! tile = 0
+//! ```
+//!
+//! relative paths are taken from the directory of the workspace file
+
+use crate::db::{DbTile, TileState};
+use egui_tiles as et;
+use std::path::{Path, PathBuf};
+
+/// how many recent workspaces the file menu lists
+const RECENT: usize = 8;
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    /// files and directories scanned for the side panel
+    pub roots: Vec<PathBuf>,
+    /// the open tiles, the layout points to them by index
+    pub tiles: Vec<Tile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<Layout>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Tile {
+    pub path: PathBuf,
+    /// the integrity report of the file instead of its database
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub report: bool,
+    #[serde(flatten)]
+    pub state: TileState,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Layout {
+    Pane {
+        tile: usize,
+    },
+    Tabs {
+        children: Vec<Layout>,
+        /// index of the open tab
+        #[serde(default)]
+        active: usize,
+    },
+    Horizontal {
+        children: Vec<Layout>,
+        shares: Vec<f32>,
+    },
+    Vertical {
+        children: Vec<Layout>,
+        shares: Vec<f32>,
+    },
+    Grid {
+        children: Vec<Layout>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<usize>,
+    },
+}
+
+fn resolve(base: &Path, path: &Path) -> PathBuf {
+    if path.is_relative() {
+        base.join(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+impl Workspace {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {path:?}: {e}"))?;
+        let mut ws = toml::from_str::<Self>(&data)
+            .map_err(|e| format!("bad workspace {path:?}: {e}"))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for root in ws.roots.iter_mut() {
+            *root = resolve(base, root);
+        }
+        for tile in ws.tiles.iter_mut() {
+            tile.path = resolve(base, &tile.path);
+        }
+        Ok(ws)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let data = toml::to_string_pretty(self)
+            .map_err(|e| format!("could not encode the workspace: {e}"))?;
+        std::fs::write(path, data)
+            .map_err(|e| format!("could not write {path:?}: {e}"))
+    }
+
+    /// the open tiles of `tree` and their layout
+    pub fn capture(tree: &et::Tree<DbTile>, roots: &[PathBuf]) -> Self {
+        let mut ws = Self { roots: roots.to_vec(), ..Default::default() };
+        ws.layout = tree.root.and_then(|id| ws.layout(tree, id));
+        ws
+    }
+
+    fn layout(
+        &mut self, tree: &et::Tree<DbTile>, id: et::TileId,
+    ) -> Option<Layout> {
+        let mut children = |ids: &[et::TileId]| -> Vec<Layout> {
+            ids.iter().filter_map(|c| self.layout(tree, *c)).collect()
+        };
+        Some(match tree.tiles.get(id)? {
+            et::Tile::Pane(db) => {
+                self.tiles.push(Tile {
+                    path: db.path.clone(),
+                    report: db.is_report(),
+                    state: db.state(),
+                });
+                Layout::Pane { tile: self.tiles.len() - 1 }
+            }
+            et::Tile::Container(et::Container::Tabs(tabs)) => {
+                let active = tabs
+                    .active
+                    .and_then(|a| tabs.children.iter().position(|c| *c == a));
+                Layout::Tabs {
+                    children: children(&tabs.children),
+                    active: active.unwrap_or_default(),
+                }
+            }
+            et::Tile::Container(et::Container::Linear(linear)) => {
+                let shares =
+                    linear.children.iter().map(|c| linear.shares[*c]).collect();
+                let children = children(&linear.children);
+                match linear.dir {
+                    et::LinearDir::Horizontal => {
+                        Layout::Horizontal { children, shares }
+                    }
+                    et::LinearDir::Vertical => {
+                        Layout::Vertical { children, shares }
+                    }
+                }
+            }
+            et::Tile::Container(et::Container::Grid(grid)) => {
+                let ids = grid.children().copied().collect::<Vec<_>>();
+                let columns = match grid.layout {
+                    et::GridLayout::Auto => None,
+                    et::GridLayout::Columns(n) => Some(n),
+                };
+                Layout::Grid { children: children(&ids), columns }
+            }
+        })
+    }
+
+    /// opens the tiles and lays them out. tiles that can not be opened
+    /// are left out, their errors are returned with the tree
+    pub fn restore(
+        &self, ctx: &egui::Context,
+    ) -> (et::Tree<DbTile>, Vec<String>) {
+        let mut errors = Vec::new();
+        let mut panes = self
+            .tiles
+            .iter()
+            .map(|tile| {
+                let db = if tile.report {
+                    Ok(DbTile::report(tile.path.clone()))
+                } else {
+                    DbTile::new(tile.path.clone(), ctx)
+                };
+                match db {
+                    Ok(mut db) => {
+                        db.restore(&tile.state, ctx);
+                        Some(db)
+                    }
+                    Err(e) => {
+                        errors.push(format!(
+                            "could not open {:?}: {e:?}",
+                            tile.path
+                        ));
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut tiles = et::Tiles::default();
+        let mut root = self
+            .layout
+            .as_ref()
+            .and_then(|l| Self::build(l, &mut panes, &mut tiles));
+        // tiles the layout does not place go next to it
+        let rest = panes
+            .into_iter()
+            .flatten()
+            .map(|db| tiles.insert_pane(db))
+            .collect::<Vec<_>>();
+        if !rest.is_empty() {
+            let children = root.into_iter().chain(rest).collect();
+            root = Some(tiles.insert_horizontal_tile(children));
+        }
+
+        let tree = match root {
+            Some(root) => et::Tree::new("main-tree", root, tiles),
+            None => et::Tree::empty("main-tree"),
+        };
+        (tree, errors)
+    }
+
+    fn build(
+        layout: &Layout, panes: &mut [Option<DbTile>],
+        tiles: &mut et::Tiles<DbTile>,
+    ) -> Option<et::TileId> {
+        let mut build = |list: &[Layout]| -> Vec<Option<et::TileId>> {
+            list.iter().map(|l| Self::build(l, panes, tiles)).collect()
+        };
+        let container = match layout {
+            Layout::Pane { tile } => {
+                let db = panes.get_mut(*tile)?.take()?;
+                return Some(tiles.insert_pane(db));
+            }
+            Layout::Tabs { children, active } => {
+                let ids = build(children);
+                let mut tabs =
+                    et::Tabs::new(ids.iter().flatten().copied().collect());
+                if let Some(Some(id)) = ids.get(*active) {
+                    tabs.set_active(*id);
+                }
+                et::Container::Tabs(tabs)
+            }
+            Layout::Horizontal { children, shares }
+            | Layout::Vertical { children, shares } => {
+                let dir = match layout {
+                    Layout::Vertical { .. } => et::LinearDir::Vertical,
+                    _ => et::LinearDir::Horizontal,
+                };
+                let ids = build(children);
+                let mut linear = et::Linear::new(dir, Vec::new());
+                for (i, id) in ids.into_iter().enumerate() {
+                    let Some(id) = id else { continue };
+                    linear.children.push(id);
+                    if let Some(share) = shares.get(i) {
+                        linear.shares.set_share(id, *share);
+                    }
+                }
+                et::Container::Linear(linear)
+            }
+            Layout::Grid { children, columns } => {
+                let ids = build(children).into_iter().flatten().collect();
+                let mut grid = et::Grid::new(ids);
+                if let Some(n) = columns {
+                    grid.layout = et::GridLayout::Columns(*n);
+                }
+                et::Container::Grid(grid)
+            }
+        };
+        if container.is_empty() {
+            return None;
+        }
+        Some(tiles.insert_container(container))
+    }
+}
+
+fn recent_id() -> egui::Id {
+    egui::Id::new("recent-workspaces")
+}
+
+/// the last opened or saved workspaces, newest first
+pub fn recent(ctx: &egui::Context) -> Vec<PathBuf> {
+    ctx.memory_mut(|mem| mem.data.get_persisted(recent_id()))
+        .unwrap_or_default()
+}
+
+pub fn remember(ctx: &egui::Context, path: &Path) {
+    let path = std::path::absolute(path).unwrap_or(path.to_path_buf());
+    let mut list = recent(ctx);
+    list.retain(|p| *p != path);
+    list.insert(0, path);
+    list.truncate(RECENT);
+    ctx.memory_mut(|mem| mem.data.insert_persisted(recent_id(), list));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    /// the database at `path` once it is open
+    fn opened(path: &Path, ctx: &egui::Context) -> DbTile {
+        let mut db = DbTile::new(path.to_path_buf(), ctx).unwrap();
+        while !db.opened(ctx) {
+            std::thread::yield_now();
+        }
+        db
+    }
+
+    /// the item entity and its report as tabs above the trie const
+    fn tree(ctx: &egui::Context) -> et::Tree<DbTile> {
+        let fx = fixtures::get();
+        let mut tiles = et::Tiles::default();
+        let item = tiles.insert_pane(opened(&fx.item, ctx));
+        let report = tiles.insert_pane(DbTile::report(fx.item.clone()));
+        let trie = tiles.insert_pane(opened(&fx.trie, ctx));
+        let tabs = tiles.insert_tab_tile(vec![item, report]);
+        let mut linear =
+            et::Linear::new(et::LinearDir::Vertical, vec![tabs, trie]);
+        linear.shares.set_share(trie, 2.0);
+        let root = tiles.insert_container(linear);
+        et::Tree::new("main-tree", root, tiles)
+    }
+
+    #[test]
+    fn round_trip() {
+        let fx = fixtures::get();
+        let ctx = egui::Context::default();
+        let mut tree = tree(&ctx);
+        let state = TileState {
+            skip: Some(2),
+            sort: vec!["-age".into(), "name".into()],
+            fields: Some(vec!["name".into(), "age".into()]),
+            filter: "age > 10".into(),
+            ..Default::default()
+        };
+        for (_, tile) in tree.tiles.iter_mut() {
+            if let et::Tile::Pane(db) = tile {
+                if db.path == fx.item && !db.is_report() {
+                    db.restore(&state, &ctx);
+                }
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("round_trip.toml");
+        let roots = vec![fx.data.clone()];
+        Workspace::capture(&tree, &roots).save(&path).unwrap();
+        let ws = Workspace::load(&path).unwrap();
+        assert_eq!(ws.roots, roots);
+        assert_eq!(ws.tiles.len(), 3);
+        assert_eq!(ws.tiles[0].state, state);
+        assert!(ws.tiles[1].report);
+        assert_eq!(ws.tiles[2].path, fx.trie);
+        assert_eq!(ws.tiles[2].state.show, Some(10));
+
+        let (tree, errors) = ws.restore(&ctx);
+        assert!(errors.is_empty(), "{errors:?}");
+        let again = Workspace::capture(&tree, &roots);
+        assert_eq!(again.layout, ws.layout);
+        assert_eq!(again.tiles[0].state, state);
+        let Some(Layout::Vertical { shares, .. }) = &again.layout else {
+            panic!("{:?}", again.layout);
+        };
+        assert_eq!(shares, &[1.0, 2.0]);
+    }
+
+    #[test]
+    fn missing_tile() {
+        let fx = fixtures::get();
+        let dir = tempfile::tempdir().unwrap();
+        let names = dir.path().join("shop/names.shah");
+        std::fs::create_dir_all(names.parent().unwrap()).unwrap();
+        std::fs::copy(&fx.trie, &names).unwrap();
+        let path = dir.path().join("missing.toml");
+        std::fs::write(
+            &path,
+            "[[tiles]]\npath = \"gone/gone.0.shah\"\n\n\
+             [[tiles]]\npath = \"shop/names.shah\"\nskip = 3\n\n\
+             [layout]\nkind = \"tabs\"\nactive = 1\n\n\
+             [[layout.children]]\nkind = \"pane\"\ntile = 0\n\n\
+             [[layout.children]]\nkind = \"pane\"\ntile = 1\n",
+        )
+        .unwrap();
+        let ws = Workspace::load(&path).unwrap();
+        assert_eq!(ws.tiles[1].path, names);
+
+        let (tree, errors) = ws.restore(&egui::Context::default());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("gone.0.shah"), "{errors:?}");
+        let again = Workspace::capture(&tree, &[]);
+        assert_eq!(again.tiles.len(), 1);
+        assert_eq!(again.tiles[0].state.skip, Some(3));
+        assert!(matches!(
+            again.layout,
+            Some(Layout::Tabs { ref children, active: 0 }) if children.len() == 1
+        ));
+
+        assert!(Workspace::load(&dir.path().join("none.toml")).is_err());
+    }
+}